@@ -1,7 +1,104 @@
-use super::{conversions::lch_to_lab};
+use palette::{Srgb, LinSrgb, Lab, Lch, Oklab, FromColor};
+
+use super::conversions::{lch_to_lab, ColourSpace};
 
 type Colour = (f32, f32, f32);
 
+/// The metric used to measure how far apart two colours are - which decides what the
+/// "closest" palette colour is during quantization.
+///
+/// Perceptual metrics tend to give noticeably different results on small palettes, at
+/// the cost of converting every colour into their respective colour space first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColourDistance {
+    /// Weighted euclidean distance in sRGB. See [`rgb_weighted_euclidean`]. This is the default.
+    #[default]
+    WeightedRgb,
+
+    /// Euclidean distance in linear RGB.
+    LinearRgb,
+
+    /// Euclidean distance in CIELAB. See [`cie76`].
+    Cie76,
+
+    /// The CIE94 formula on CIELCh. See [`cie94`].
+    Cie94,
+
+    /// The CIEDE2000 formula on CIELCh. See [`ciede2000`].
+    Ciede2000,
+
+    /// Euclidean distance in Oklab.
+    Oklab,
+
+    /// The HyAB distance in CIELAB. See [`hyab`].
+    HyAB,
+
+    /// The difference in Rec. 709 relative luminance, ignoring colour entirely.
+    Luminance,
+
+    /// The difference in CIELAB lightness (`L*`), ignoring colour entirely.
+    Lightness,
+}
+
+impl ColourDistance {
+    /// Converts the colour into the space this metric operates in.
+    ///
+    /// Useful for converting a palette once, rather than on every comparison.
+    pub fn prepare(&self, colour: Srgb) -> Colour {
+        match self {
+            Self::WeightedRgb => colour.into_components(),
+            Self::LinearRgb => LinSrgb::from_color(colour).into_components(),
+            Self::Cie76 | Self::HyAB => Lab::from_color(colour).into_components(),
+            Self::Cie94 | Self::Ciede2000 => {
+                let lch = Lch::from_color(colour);
+                (lch.l, lch.chroma, lch.hue.into_positive_degrees())
+            },
+            Self::Oklab => Oklab::from_color(colour).into_components(),
+            Self::Luminance => ColourSpace::Luminance.from_srgb(colour),
+            Self::Lightness => ColourSpace::Lightness.from_srgb(colour),
+        }
+    }
+
+    /// Returns the colour space that [`ColourDistance::prepare`] converts into - if it's one of the
+    /// supported [`ColourSpace`]s.
+    pub fn colour_space(&self) -> Option<ColourSpace> {
+        match self {
+            Self::WeightedRgb => Some(ColourSpace::Srgb),
+            Self::LinearRgb => Some(ColourSpace::LinearRgb),
+            Self::Cie76 | Self::HyAB => Some(ColourSpace::Lab),
+            Self::Oklab => Some(ColourSpace::Oklab),
+            Self::Luminance => Some(ColourSpace::Luminance),
+            Self::Lightness => Some(ColourSpace::Lightness),
+            Self::Cie94 | Self::Ciede2000 => None,
+        }
+    }
+
+    /// Compares two colours that have already been converted using [`ColourDistance::prepare`].
+    pub fn compare(&self, a: Colour, b: Colour) -> f32 {
+        match self {
+            Self::WeightedRgb => rgb_weighted_euclidean(a, b),
+            Self::LinearRgb | Self::Cie76 | Self::Oklab | Self::Luminance | Self::Lightness => euclidean(a, b),
+            Self::Cie94 => cie94(a, b),
+            Self::Ciede2000 => ciede2000(a, b),
+            Self::HyAB => hyab(a, b),
+        }
+    }
+
+    /// Calculates the distance between two sRGB colours using this metric.
+    pub fn distance(&self, a: Srgb, b: Srgb) -> f32 {
+        self.compare(self.prepare(a), self.prepare(b))
+    }
+}
+
+/// Calculates the plain euclidean distance between two colours of the same space.
+pub fn euclidean(a: Colour, b: Colour) -> f32 {
+    (
+          (b.0 - a.0).powi(2)
+        + (b.1 - a.1).powi(2)
+        + (b.2 - a.2).powi(2)
+    ).sqrt()
+}
+
 /// Calculates the distance between two RGB colours using the euclidean distance function.
 /// 
 /// Also includes weight to more accurately calculate the distance.
@@ -16,23 +113,35 @@ pub fn rgb_weighted_euclidean(rgb_a: Colour, rgb_b: Colour) -> f32 {
     diff_r + diff_g + diff_b
 }
 
+/// Calculates the distance between two LAB colours using CIE76 - which is just the euclidean distance.
 pub fn cie76(lab_a: Colour, lab_b: Colour) -> f32 {
-    (
-          (lab_b.0 - lab_a.0).powi(2)
-        + (lab_b.1 - lab_a.1).powi(2)
-        + (lab_b.2 - lab_a.2).powi(2)
-    ).sqrt()
+    euclidean(lab_a, lab_b)
+}
+
+/// Calculates the distance between two LAB colours using HyAB.
+///
+/// Combines the absolute lightness difference with the euclidean distance of the chromatic
+/// components, which handles large colour differences better than CIE76.
+pub fn hyab(lab_a: Colour, lab_b: Colour) -> f32 {
+    (lab_b.0 - lab_a.0).abs()
+        + ((lab_b.1 - lab_a.1).powi(2) + (lab_b.2 - lab_a.2).powi(2)).sqrt()
 }
 
-/// This function may not work correctly.
+/// Calculates the distance between two LCH colours using CIE94, with the graphic arts constants.
+///
+/// The first colour is treated as the reference, so the metric isn't symmetric.
 pub fn cie94(lch_a: Colour, lch_b: Colour) -> f32 {
     const K_L: f32 = 1.0;
     const K_C: f32 = 1.0;
     const K_H: f32 = 1.0;
 
+    let (_, a_1, b_1) = lch_to_lab(lch_a);
+    let (_, a_2, b_2) = lch_to_lab(lch_b);
+
     let delta_l = lch_a.0 - lch_b.0;
     let delta_c = lch_a.1 - lch_b.1;
-    let delta_h = lch_a.2 - lch_b.2;
+    // ΔH² = ΔE² - ΔL² - ΔC², which reduces to Δa² + Δb² - ΔC² - rather than the difference of hue angles
+    let delta_h_squared = ((a_1 - a_2).powi(2) + (b_1 - b_2).powi(2) - delta_c.powi(2)).max(0.0);
 
     let (s_l, s_c, s_h) = (
         1.0,
@@ -43,14 +152,13 @@ pub fn cie94(lch_a: Colour, lch_b: Colour) -> f32 {
     (
           (delta_l / (K_L * s_l)).powi(2)
         + (delta_c / (K_C * s_c)).powi(2)
-        + (delta_h / (K_H * s_h)).powi(2)
+        + delta_h_squared / (K_H * s_h).powi(2)
     ).sqrt()
 }
 
 /// Calculates the distance between two LCH colours using CIEDE2000.
 /// 
-/// Not confirmed to be fully functional yet - however this algorithm is 
-/// proven to be the best, albeit significantly slower due to more computations.
+/// This algorithm is proven to be the best, albeit significantly slower due to more computations.
 pub fn ciede2000(lch_a: Colour, lch_b: Colour) -> f32 {
     // set up constants for formula
     // these are usually unity (1)
@@ -68,7 +176,7 @@ pub fn ciede2000(lch_a: Colour, lch_b: Colour) -> f32 {
     let avg_l = (lch_b.0 + lch_a.0) / 2.0;
     let avg_c = (lch_b.1 + lch_a.1) / 2.0;
 
-    let c_7_mul = 1.0 - (avg_c.powi(7) / (avg_c.powi(7) + 25_f32.powi(7)).sqrt());
+    let c_7_mul = 1.0 - (avg_c.powi(7) / (avg_c.powi(7) + 25_f32.powi(7))).sqrt();
     let a_1_mark = a_1 + (a_1 / 2.0) * c_7_mul;
     let a_2_mark = a_2 + (a_2 / 2.0) * c_7_mul;
 
@@ -78,8 +186,8 @@ pub fn ciede2000(lch_a: Colour, lch_b: Colour) -> f32 {
     let delta_c_mark = c_2_mark - c_1_mark;
     let avg_c_mark = (c_2_mark + c_1_mark) / 2.0;
 
-    let h_1_mark = b_1.atan2(a_1_mark).to_degrees() % 360.0;
-    let h_2_mark = b_1.atan2(a_1_mark).to_degrees() % 360.0;
+    let h_1_mark = b_1.atan2(a_1_mark).to_degrees().rem_euclid(360.0);
+    let h_2_mark = b_2.atan2(a_2_mark).to_degrees().rem_euclid(360.0);
 
     let abs_diff_h_marks = (h_1_mark - h_2_mark).abs();
     let delta_h_mark = 
@@ -113,7 +221,7 @@ pub fn ciede2000(lch_a: Colour, lch_b: Colour) -> f32 {
 
     let s_l = 1.0
         + (0.015 * (avg_l - 50.0).powi(2))
-        / (20.0 + (avg_l - 50.0).powi(2));
+        / (20.0 + (avg_l - 50.0).powi(2)).sqrt();
 
     let s_c = 1.0 + 0.045 * avg_c_mark;
     let s_h = 1.0 + 0.015 * avg_c_mark * t;
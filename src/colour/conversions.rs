@@ -1,5 +1,79 @@
+use palette::{Srgb, LinSrgb, Lab, Oklab, FromColor, convert::FromColorUnclamped};
+
 // constants
 
+/// The colour space that dithering arithmetic - such as error, thresholds and mixing - is performed in.
+///
+/// Palettes and pixels are converted into this space once, and only converted back to sRGB for output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColourSpace {
+    /// Gamma-encoded sRGB. This is the default.
+    #[default]
+    Srgb,
+
+    /// Linear-light RGB, using the sRGB transfer function.
+    ///
+    /// Mixing in linear light keeps the perceived brightness of dithered areas close to the original
+    /// when viewed at a distance, whereas sRGB mixing makes mid-tones come out too dark.
+    LinearRgb,
+
+    /// The Oklab perceptual colour space.
+    ///
+    /// Propagating error perceptually avoids the hue shifts sRGB error causes with small, hue-heavy palettes.
+    Oklab,
+
+    /// The CIELAB perceptual colour space, using the D65 white point.
+    Lab,
+
+    /// Relative luminance only, as defined by Rec. 709 - discarding colour entirely.
+    ///
+    /// Colours are represented as `(luminance, 0.0, 0.0)`, and converted back as grays of the same luminance.
+    /// Pair it with `ColourDistance::Luminance` so that the closest colour is measured by luminance as well -
+    /// `with_luminance` on the ditherers sets both at once.
+    Luminance,
+
+    /// Perceptual lightness only, as the `L*` of CIELAB - discarding colour entirely.
+    ///
+    /// Colours are represented as `(lightness, 0.0, 0.0)`, and converted back as grays of the same lightness.
+    /// Pair it with `ColourDistance::Lightness` so that the closest colour is measured by lightness as well -
+    /// `with_lightness` on the ditherers sets both at once.
+    Lightness,
+}
+
+impl ColourSpace {
+    /// Converts an sRGB colour into this space.
+    pub fn from_srgb(&self, colour: Srgb) -> (f32, f32, f32) {
+        match self {
+            Self::Srgb => colour.into_components(),
+            Self::LinearRgb => colour.into_linear::<f32>().into_components(),
+            Self::Oklab => Oklab::from_color(colour).into_components(),
+            Self::Lab => Lab::from_color(colour).into_components(),
+            Self::Luminance => (rec_709_luminance(colour), 0.0, 0.0),
+            Self::Lightness => (Lab::from_color(colour).l, 0.0, 0.0),
+        }
+    }
+
+    /// Converts a colour in this space back into sRGB.
+    ///
+    /// The result isn't clamped, since colours with error applied can fall out of gamut.
+    pub fn to_srgb(&self, colour: (f32, f32, f32)) -> Srgb {
+        match self {
+            Self::Srgb => Srgb::from_components(colour),
+            Self::LinearRgb => LinSrgb::from_components(colour).into_encoding(),
+            Self::Oklab => Srgb::from_color_unclamped(Oklab::from_components(colour)),
+            Self::Lab => Srgb::from_color_unclamped(Lab::from_components(colour)),
+            Self::Luminance => LinSrgb::new(colour.0, colour.0, colour.0).into_encoding(),
+            Self::Lightness => Srgb::from_color_unclamped(Lab::new(colour.0, 0.0, 0.0)),
+        }
+    }
+}
+
+/// The relative luminance of an sRGB colour, using the Rec. 709 coefficients on linear RGB.
+pub fn rec_709_luminance(colour: Srgb) -> f32 {
+    let linear = colour.into_linear::<f32>();
+    0.2126 * linear.red + 0.7152 * linear.green + 0.0722 * linear.blue
+}
+
 /// Converts LCH to LAB.
 /// 
 /// The expected ranges for LCH are `(0.0~100.0, 0.0~150.0, 0.0~360.0)`
@@ -43,3 +43,56 @@ pub static WEB_SAFE: Lazy<Vec<Srgb>> = Lazy::new(|| {
     .map(|color| hexcode_to_srgb(color))
     .collect()
 });
+
+/// Creates a palette of evenly spaced grays, from black to white - such as `4` levels for e-paper panels.
+pub fn grayscale(levels: usize) -> Vec<Srgb> {
+    let steps = levels.max(2) - 1;
+
+    (0..=steps)
+        .map(|level| {
+            let value = level as f32 / steps as f32;
+            Srgb::new(value, value, value)
+        })
+        .collect()
+}
+
+/// The ZX Spectrum palette - the eight normal colours, followed by their `BRIGHT` counterparts.
+///
+/// Black appears in both halves, so that the index of every colour maps directly onto its attribute bits.
+pub static ZX_SPECTRUM: Lazy<Vec<Srgb>> = Lazy::new(|| {
+    vec![
+        "000000", "0000D7", "D70000", "D700D7", "00D700", "00D7D7", "D7D700", "D7D7D7",
+        "000000", "0000FF", "FF0000", "FF00FF", "00FF00", "00FFFF", "FFFF00", "FFFFFF",
+    ]
+    .into_iter()
+    .map(hexcode_to_srgb)
+    .collect()
+});
+
+/// The Commodore 64 palette, as measured by Pepto - in the order of the VIC-II colour codes.
+pub static C64: Lazy<Vec<Srgb>> = Lazy::new(|| {
+    vec![
+        "000000", "FFFFFF", "68372B", "70A4B2", "6F3D86", "588D43", "352879", "B8C76F",
+        "6F4F25", "433900", "9A6759", "444444", "6C6C6C", "9AD284", "6C5EB5", "959595",
+    ]
+    .into_iter()
+    .map(hexcode_to_srgb)
+    .collect()
+});
+
+/// The NES palette of the 2C02 PPU - in the order of the hardware colour codes, including the unused blacks.
+pub static NES: Lazy<Vec<Srgb>> = Lazy::new(|| {
+    vec![
+        "7C7C7C", "0000FC", "0000BC", "4428BC", "940084", "A80020", "A81000", "881400",
+        "503000", "007800", "006800", "005800", "004058", "000000", "000000", "000000",
+        "BCBCBC", "0078F8", "0058F8", "6844FC", "D800CC", "E40058", "F83800", "E45C10",
+        "AC7C00", "00B800", "00A800", "00A844", "008888", "000000", "000000", "000000",
+        "F8F8F8", "3CBCFC", "6888FC", "9878F8", "F878F8", "F85898", "F87858", "FCA044",
+        "F8B800", "B8F818", "58D854", "58F898", "00E8D8", "787878", "000000", "000000",
+        "FCFCFC", "A4E4FC", "B8B8F8", "D8B8F8", "F8B8F8", "F8A4C0", "F0D0B0", "FCE0A8",
+        "F8D878", "D8F878", "B8F8B8", "B8F8D8", "00FCFC", "F8D8F8", "000000", "000000",
+    ]
+    .into_iter()
+    .map(hexcode_to_srgb)
+    .collect()
+});
@@ -1,6 +1,6 @@
 use palette::{Srgb, FromColor, Lch};
 
-use super::comparisons::rgb_weighted_euclidean;
+use super::{comparisons::{rgb_weighted_euclidean, ColourDistance}, conversions::ColourSpace};
 
 #[inline] pub fn collapse_angle(angle: f32) -> f32 {
     ((angle % 360.0) + 360.0) % 360.0
@@ -53,13 +53,230 @@ fn quantize_colour(
 pub fn quantize_rgb(original_rgb: Srgb, palette: &[Srgb]) -> Srgb {
     let srgb = quantize_colour(
         original_rgb.into_components(),
-        &palette.into_iter().map(|&col| col.into_components()).collect::<Vec<_>>(),
+        &palette.iter().map(|&col| col.into_components()).collect::<Vec<_>>(),
         rgb_weighted_euclidean
     );
 
     Srgb::from_components(srgb)
 }
 
+/// Quantizes the colour to the closest colour in the palette, as measured by `distance`.
+pub fn quantize_rgb_with(original_rgb: Srgb, palette: &[Srgb], distance: ColourDistance) -> Srgb {
+    PaletteMatcher::new(palette, distance).nearest(original_rgb)
+}
+
+/// A bit depth for each channel, such as `5-6-5` for 16-bit displays.
+///
+/// Quantizing to a bit depth rounds each channel to the closest of its evenly spaced levels on its own,
+/// which is much faster than searching a palette with every possible combination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitDepth {
+    bits: [u8; 3],
+}
+
+impl BitDepth {
+    /// 16-bit colour, with `5` bits for red and blue and `6` bits for green.
+    pub const RGB565: BitDepth = BitDepth::new(5, 6, 5);
+
+    /// 15-bit colour, with `5` bits for each channel.
+    pub const RGB555: BitDepth = BitDepth::new(5, 5, 5);
+
+    /// 12-bit colour, with `4` bits for each channel.
+    pub const RGB444: BitDepth = BitDepth::new(4, 4, 4);
+
+    /// 8-bit colour, with `3` bits for red and green and `2` bits for blue.
+    pub const RGB332: BitDepth = BitDepth::new(3, 3, 2);
+
+    /// Creates a bit depth with the given amount of bits for each channel, each between `1` and `8`.
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        const fn clamp(bits: u8) -> u8 {
+            if bits < 1 { 1 } else if bits > 8 { 8 } else { bits }
+        }
+
+        Self { bits: [clamp(red), clamp(green), clamp(blue)] }
+    }
+
+    /// The amount of bits of each channel.
+    pub fn bits(&self) -> [u8; 3] {
+        self.bits
+    }
+
+    /// The amount of levels of each channel.
+    pub fn levels(&self) -> [usize; 3] {
+        self.bits.map(|bits| 1 << bits)
+    }
+
+    /// The distance between two neighbouring levels of each channel, where `1.0` spans the entire channel.
+    pub fn steps(&self) -> [f32; 3] {
+        self.levels().map(|levels| 1.0 / (levels - 1) as f32)
+    }
+
+    /// Rounds each channel of the colour to its closest level.
+    pub fn nearest(&self, colour: Srgb) -> Srgb {
+        self.colour(self.index(colour))
+    }
+
+    /// Whether the colour is exactly representable at this bit depth.
+    pub fn contains(&self, colour: [u8; 3]) -> bool {
+        let rounded: [u8; 3] = self.nearest(Srgb::from(colour).into_format()).into_format().into();
+        rounded == colour
+    }
+
+    /// Every colour representable at this bit depth, as an explicit palette.
+    pub fn palette(&self) -> Vec<Srgb> {
+        let [r, g, b] = self.levels();
+        (0..r * g * b).map(|index| self.colour(index)).collect()
+    }
+
+    fn index(&self, colour: Srgb) -> usize {
+        let [r, g, b] = self.levels();
+        let level = |value: f32, levels: usize| (value.clamp(0.0, 1.0) * (levels - 1) as f32).round() as usize;
+
+        (level(colour.red, r) * g + level(colour.green, g)) * b + level(colour.blue, b)
+    }
+
+    fn colour(&self, index: usize) -> Srgb {
+        let [r, g, b] = self.levels();
+        let value = |level: usize, levels: usize| level as f32 / (levels - 1) as f32;
+
+        Srgb::new(value(index / (g * b) % r, r), value(index / b % g, g), value(index % b, b))
+    }
+}
+
+/// A palette prepared for repeatedly finding the closest colour under a given [`ColourDistance`].
+///
+/// The palette is converted into the space of the distance metric once on creation, rather than
+/// on every lookup. It can also quantize to a [`BitDepth`] instead of an explicit palette.
+#[derive(Clone)]
+pub struct PaletteMatcher {
+    palette: Vec<Srgb>,
+    prepared: Vec<(f32, f32, f32)>,
+    distance: ColourDistance,
+    bit_depth: Option<BitDepth>,
+}
+
+impl PaletteMatcher {
+    pub fn new(palette: &[Srgb], distance: ColourDistance) -> Self {
+        Self {
+            palette: palette.to_vec(),
+            prepared: palette.iter().map(|&colour| distance.prepare(colour)).collect(),
+            distance,
+            bit_depth: None,
+        }
+    }
+
+    /// Creates a matcher that quantizes each channel to the bit depth, rather than matching against a palette.
+    ///
+    /// Since channels are rounded on their own, the distance metric doesn't apply.
+    pub fn from_bit_depth(bit_depth: BitDepth) -> Self {
+        Self {
+            palette: Vec::new(),
+            prepared: Vec::new(),
+            distance: ColourDistance::default(),
+            bit_depth: Some(bit_depth),
+        }
+    }
+
+    /// The palette being matched against - which is empty when quantizing to a bit depth.
+    pub fn palette(&self) -> &[Srgb] {
+        &self.palette
+    }
+
+    /// The bit depth being quantized to, if any.
+    pub fn bit_depth(&self) -> Option<BitDepth> {
+        self.bit_depth
+    }
+
+    /// The colour at the index returned by one of the `nearest_index` methods, if any.
+    pub fn colour(&self, index: usize) -> Option<Srgb> {
+        match self.bit_depth {
+            Some(bit_depth) => Some(bit_depth.colour(index)),
+            None => self.palette.get(index).copied(),
+        }
+    }
+
+    /// The distance metric used to find the closest colour.
+    pub fn distance(&self) -> ColourDistance {
+        self.distance
+    }
+
+    /// Returns the index of the closest palette colour.
+    ///
+    /// If the palette is empty, `0` is returned.
+    pub fn nearest_index(&self, colour: Srgb) -> usize {
+        match self.bit_depth {
+            Some(bit_depth) => bit_depth.index(colour),
+            None => self.nearest_prepared_index(self.distance.prepare(colour)),
+        }
+    }
+
+    /// Returns the index of the closest palette colour, for a colour given in `space`.
+    ///
+    /// If the distance metric operates in the same space the colour is compared as-is, otherwise
+    /// it's converted back to sRGB first.
+    pub fn nearest_index_in(&self, colour: (f32, f32, f32), space: ColourSpace) -> usize {
+        if self.bit_depth.is_none() && self.distance.colour_space() == Some(space) {
+            self.nearest_prepared_index(colour)
+        } else {
+            self.nearest_index(space.to_srgb(colour))
+        }
+    }
+
+    /// Returns the closest palette colour for a colour given in `space`. See [`PaletteMatcher::nearest_index_in`].
+    pub fn nearest_in(&self, colour: (f32, f32, f32), space: ColourSpace) -> Srgb {
+        self.colour(self.nearest_index_in(colour, space)).unwrap_or_else(|| space.to_srgb(colour))
+    }
+
+    /// Returns the index of the closest palette colour out of the given indices, for a colour given in `space`.
+    ///
+    /// When quantizing to a bit depth, the candidates are ignored. If there are no candidates, `0` is returned.
+    pub fn nearest_index_among(&self, colour: (f32, f32, f32), space: ColourSpace, candidates: &[usize]) -> usize {
+        if self.bit_depth.is_some() {
+            return self.nearest_index_in(colour, space);
+        }
+
+        let colour = match self.distance.colour_space() == Some(space) {
+            true => colour,
+            false => self.distance.prepare(space.to_srgb(colour)),
+        };
+
+        let mut closest_distance = f32::MAX;
+        let mut closest_index = candidates.first().copied().unwrap_or(0);
+
+        for &i in candidates {
+            let Some(candidate) = self.prepared.get(i) else { continue };
+
+            let distance = self.distance.compare(colour, *candidate);
+            if distance < closest_distance {
+                closest_index = i;
+                closest_distance = distance;
+            }
+        }
+
+        closest_index
+    }
+
+    fn nearest_prepared_index(&self, colour: (f32, f32, f32)) -> usize {
+        let mut closest_distance = f32::MAX;
+        let mut closest_index = 0;
+
+        for (i, candidate) in self.prepared.iter().enumerate() {
+            let distance = self.distance.compare(colour, *candidate);
+            if distance < closest_distance {
+                closest_index = i;
+                closest_distance = distance;
+            }
+        }
+
+        closest_index
+    }
+
+    /// Returns the closest palette colour - or the colour itself if the palette is empty.
+    pub fn nearest(&self, colour: Srgb) -> Srgb {
+        self.colour(self.nearest_index(colour)).unwrap_or(colour)
+    }
+}
+
 pub fn compute_rgb_error(main: Srgb, other: Srgb) -> (f32, f32, f32) {
     (main - other).into_components()
 }
@@ -0,0 +1,143 @@
+use palette::{Srgb, Srgba, WithAlpha};
+
+use crate::{
+    utils::image::{get_dimensions_of_matrix, RgbImageRepr, RgbaImageRepr},
+    effect::Effect,
+};
+
+use super::{
+    bayer::Bayer,
+    indexed::{IndexedDither, IndexedError, IndexedImage},
+    knoll::Knoll,
+    ordered::{Ordered, ThresholdMap, ThresholdMatrix},
+    yliluoma::Yliluoma,
+};
+
+/// Defines a ditherer that can leave out some of the pixels of an image.
+///
+/// Excluded pixels are left as-is, and they neither receive nor spread any error - so for example a transparent
+/// background doesn't bleed into the edges of a sprite.
+pub trait MaskedDither: Effect<RgbImageRepr> {
+    /// Dithers the image, leaving out the pixels where the `mask` is `false`.
+    ///
+    /// The mask must have the same dimensions as the image. Defaults to dithering every pixel, which is correct
+    /// for ditherers that only look at each pixel on its own - such as the ordered ones.
+    fn dither_masked(&self, image: RgbImageRepr, _mask: &[Vec<bool>]) -> RgbImageRepr {
+        self.affect(image)
+    }
+}
+
+impl MaskedDither for Bayer {}
+impl<M: ThresholdMap> MaskedDither for Ordered<M> {}
+impl<M: ThresholdMap> MaskedDither for Knoll<M> {}
+impl<M: ThresholdMap> MaskedDither for Yliluoma<M> {}
+
+/// Wraps a ditherer so that it takes the alpha channel into account, rather than dithering colours regardless of it.
+///
+/// Fully transparent pixels are left out of the dithering entirely, and can be replaced with a dedicated transparent
+/// palette colour - such as the transparent index of a GIF. The alpha channel itself can also be dithered down to
+/// a number of levels, for formats that only support 1-bit transparency.
+///
+/// Since it needs the alpha channel, this only works on images with one - such as `RgbaImageRepr` and `Frame`.
+#[derive(Clone)]
+pub struct AlphaAware<D: MaskedDither> {
+    ditherer: D,
+    alpha_levels: Option<usize>,
+    alpha_map: ThresholdMatrix,
+    transparent_colour: Option<Srgb>,
+}
+
+impl<D: MaskedDither> AlphaAware<D> {
+    /// Creates a new `AlphaAware` ditherer, which leaves the alpha channel as-is.
+    pub fn new(ditherer: D) -> Self {
+        Self {
+            ditherer,
+            alpha_levels: None,
+            alpha_map: ThresholdMatrix::bayer(8),
+            transparent_colour: None,
+        }
+    }
+
+    /// Creates a clone of the ditherer that dithers the alpha channel down to the given amount of evenly
+    /// spaced levels - `2` resulting in 1-bit transparency.
+    ///
+    /// Pixels that end up fully transparent are left out of the colour dithering.
+    pub fn with_alpha_levels(&self, levels: usize) -> Self where D: Clone {
+        Self { alpha_levels: Some(levels.max(2)), ditherer: self.ditherer.clone(), alpha_map: self.alpha_map.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different threshold map for dithering the alpha channel.
+    /// Defaults to `ThresholdMatrix::bayer(8)`.
+    pub fn with_alpha_map(&self, alpha_map: ThresholdMatrix) -> Self where D: Clone {
+        Self { alpha_map, ditherer: self.ditherer.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that replaces fully transparent pixels with the given colour.
+    ///
+    /// This is the transparent entry of the palette, so it should be left out of the palette of the wrapped
+    /// ditherer - otherwise opaque pixels could end up using it too. Without one, fully transparent pixels
+    /// keep their original colour.
+    pub fn with_transparent_colour(&self, colour: Srgb) -> Self where D: Clone {
+        Self { transparent_colour: Some(colour), ditherer: self.ditherer.clone(), alpha_map: self.alpha_map.clone(), ..*self }
+    }
+
+    fn dither_alpha(&self, alpha: u8, x: usize, y: usize) -> u8 {
+        let Some(levels) = self.alpha_levels else { return alpha };
+
+        let steps = (levels - 1) as f32;
+        let level = alpha as f32 / 255.0 * steps;
+        let base = level.floor();
+        let level = if level - base > self.alpha_map.threshold(x, y) { base + 1.0 } else { base };
+
+        (level / steps * 255.0).round() as u8
+    }
+}
+
+impl<D: MaskedDither> Effect<RgbaImageRepr> for AlphaAware<D> {
+    fn affect(&self, image: RgbaImageRepr) -> RgbaImageRepr {
+        let (xdim, ydim) = get_dimensions_of_matrix(&image);
+
+        let alpha: Vec<Vec<u8>> = (0..ydim)
+            .map(|y| (0..xdim).map(|x| self.dither_alpha(image[y][x][3], x, y)).collect())
+            .collect();
+
+        let mask: Vec<Vec<bool>> = alpha.iter().map(|row| row.iter().map(|alpha| *alpha > 0).collect()).collect();
+        let rgb: RgbImageRepr = image.iter().map(|row| row.iter().map(|[r, g, b, _]| [*r, *g, *b]).collect()).collect();
+
+        let rgb = self.ditherer.dither_masked(rgb, &mask);
+        let transparent: Option<[u8; 3]> = self.transparent_colour.map(|colour| colour.into_format().into());
+
+        // ditherers are free to dither the left out pixels too, so those are taken from the original image
+        (0..ydim)
+            .map(|y| {
+                (0..xdim)
+                    .map(|x| {
+                        let [r, g, b, _] = image[y][x];
+                        let [r, g, b] = match (mask[y][x], transparent) {
+                            (true, _) => rgb[y][x],
+                            (false, Some(colour)) => colour,
+                            (false, None) => [r, g, b],
+                        };
+                        [r, g, b, alpha[y][x]]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<D: MaskedDither + IndexedDither> AlphaAware<D> {
+    /// Dithers the image, returning the index of the palette colour picked for each pixel alongside the palette.
+    ///
+    /// The palette is the one of the wrapped ditherer, followed by the transparent colour with an alpha of `0`
+    /// if there is one. Pixels that aren't fully opaque or fully transparent get an entry for each level of alpha -
+    /// which fails if there are more than `256` colours in total.
+    pub fn dither_indexed(&self, image: RgbaImageRepr) -> Result<IndexedImage, IndexedError> {
+        let mut palette: Vec<Srgba> = self.ditherer.palette().iter().map(|colour| colour.with_alpha(1.0)).collect();
+        if let Some(colour) = self.transparent_colour {
+            palette.push(colour.with_alpha(0.0));
+        }
+
+        IndexedImage::from_rgba(&self.affect(image), &palette)
+    }
+}
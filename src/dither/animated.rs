@@ -0,0 +1,81 @@
+use image::{Frame, RgbaImage};
+
+use crate::effect::Effect;
+
+/// Wraps a ditherer so that it dithers an entire animation at once, keeping the output of unchanged
+/// regions identical from frame to frame.
+///
+/// Dithering each frame on its own causes flicker - especially with error propagation, where a single changed
+/// pixel can alter the pattern across the entire rest of the image. Instead, every pixel whose input hasn't changed
+/// since its output was last chosen simply reuses the output of the previous frame.
+///
+/// Ordered ditherers such as `Bayer` are already stable on their own, since each pixel only depends on its own
+/// colour and position - but still benefit from this when the input changes ever so slightly between frames.
+#[derive(Clone)]
+pub struct Animated<E: Effect<RgbaImage>> {
+    ditherer: E,
+    tolerance: u8,
+}
+
+impl<E: Effect<RgbaImage>> Animated<E> {
+    /// Creates a new `Animated` ditherer, using the given ditherer for each frame.
+    pub fn new(ditherer: E) -> Self {
+        Self { ditherer, tolerance: 0 }
+    }
+
+    /// Creates a clone of the ditherer where a pixel counts as unchanged as long as none of its channels
+    /// differ by more than the tolerance. Defaults to `0`.
+    ///
+    /// Useful for sources with noise or compression artifacts. The comparison is always made against the input the
+    /// output was chosen for, so slow fades still update once they have drifted far enough.
+    pub fn with_tolerance(&self, tolerance: u8) -> Self where E: Clone {
+        Self { ditherer: self.ditherer.clone(), tolerance }
+    }
+
+    fn is_unchanged(&self, current: [u8; 4], reference: [u8; 4]) -> bool {
+        // fully transparent pixels look the same regardless of their colour
+        if current[3] == 0 && reference[3] == 0 {
+            return true;
+        }
+
+        current.iter().zip(reference.iter()).all(|(a, b)| a.abs_diff(*b) <= self.tolerance)
+    }
+}
+
+impl<E: Effect<RgbaImage>> Effect<Vec<Frame>> for Animated<E> {
+    fn affect(&self, frames: Vec<Frame>) -> Vec<Frame> {
+        // the input each output pixel was chosen for, alongside the output of the previous frame
+        let mut previous: Option<(u32, u32, RgbaImage, RgbaImage)> = None;
+        let mut output = Vec::with_capacity(frames.len());
+
+        for frame in frames {
+            let (left, top, delay) = (frame.left(), frame.top(), frame.delay());
+            let input = frame.into_buffer();
+            let mut dithered = self.ditherer.affect(input.clone());
+
+            let reference = match previous.take() {
+                Some((previous_left, previous_top, mut reference, previous_output))
+                    if (previous_left, previous_top) == (left, top) && reference.dimensions() == input.dimensions() =>
+                {
+                    for (x, y, pixel) in dithered.enumerate_pixels_mut() {
+                        let reference = reference.get_pixel_mut(x, y);
+
+                        if self.is_unchanged(input.get_pixel(x, y).0, reference.0) {
+                            *pixel = *previous_output.get_pixel(x, y);
+                        } else {
+                            *reference = *input.get_pixel(x, y);
+                        }
+                    }
+
+                    reference
+                },
+                _ => input,
+            };
+
+            previous = Some((left, top, reference, dithered.clone()));
+            output.push(Frame::from_parts(dithered, left, top, delay));
+        }
+
+        output
+    }
+}
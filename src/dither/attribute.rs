@@ -0,0 +1,796 @@
+use std::{collections::BTreeMap, fs::File, io::{BufWriter, Write}, path::Path};
+
+use image::{error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind}, ImageError, ImageResult};
+use palette::Srgb;
+
+use crate::{
+    utils::image::{get_dimensions_of_matrix, RgbImageRepr},
+    colour::{palettes, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::{
+    error::{ErrorPropagator, Base, PixelGuide, FLOYD_STEINBERG},
+    indexed::{IndexedDither, IndexedError},
+};
+
+/// Above this many possible subsets per cell, a greedy search is used instead of trying every subset.
+const EXHAUSTIVE_LIMIT: usize = 4096;
+
+/// How many times the subsets are improved by swapping out single colours, at most.
+const MAX_SWAP_PASSES: usize = 8;
+
+/// How many times shared subsets are reassigned and re-fitted to their cells, at most.
+const MAX_REFINEMENTS: usize = 8;
+
+/// How much the distance to the closest single colour counts towards the cost of a subset, relative to the
+/// distance to the closest mix of two colours.
+const NEAREST_WEIGHT: f32 = 0.25;
+
+/// The colour shared by every cell of an [`AttributeLayout`], on top of the colours picked for each cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    /// Every colour of a cell is picked for that cell alone. This is the default.
+    #[default]
+    None,
+
+    /// The palette colour at the given index is shared by every cell.
+    Fixed(usize),
+
+    /// The palette colour closest to the most pixels of the image is shared by every cell.
+    Best,
+}
+
+/// Describes the colour constraints of a display mode with _attribute clash_ - where the image is split into cells,
+/// and each cell can only use a few colours out of the palette.
+///
+/// There are presets for common machines, such as [`AttributeLayout::zx_spectrum`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeLayout {
+    palette: Vec<Srgb>,
+    cell_size: (usize, usize),
+    pixel_width: usize,
+    colours: usize,
+    background: Background,
+    groups: Vec<Vec<usize>>,
+    subsets: Option<usize>,
+}
+
+impl AttributeLayout {
+    /// Creates a layout of `(width, height)` cells, each of which can use `colours` colours out of the palette.
+    pub fn new(palette: Vec<Srgb>, cell_size: (usize, usize), colours: usize) -> Self {
+        Self {
+            palette,
+            cell_size: (cell_size.0.max(1), cell_size.1.max(1)),
+            pixel_width: 1,
+            colours: colours.max(1),
+            background: Background::None,
+            groups: Vec::new(),
+            subsets: None,
+        }
+    }
+
+    /// The ZX Spectrum: 8x8 cells of an ink and a paper colour, which are either both normal or both `BRIGHT`.
+    ///
+    /// Meant for 256x192 images, which can be exported with [`AttributeImage::write_scr`].
+    pub fn zx_spectrum() -> Self {
+        Self::new(palettes::ZX_SPECTRUM.to_vec(), (8, 8), 2)
+            .with_groups(vec![(0..8).collect(), (8..16).collect()])
+    }
+
+    /// The Commodore 64 in hires bitmap mode: 8x8 cells of two colours each.
+    ///
+    /// Meant for 320x200 images.
+    pub fn c64_hires() -> Self {
+        Self::new(palettes::C64.to_vec(), (8, 8), 2)
+    }
+
+    /// The Commodore 64 in multicolour bitmap mode: double-wide pixels, in 4x8 cells of three colours each
+    /// alongside a shared background colour.
+    ///
+    /// Meant for 320x200 images, which can be exported with [`AttributeImage::write_koala`].
+    pub fn c64_multicolour() -> Self {
+        Self::new(palettes::C64.to_vec(), (4, 8), 4)
+            .with_pixel_width(2)
+            .with_background(Background::Best)
+    }
+
+    /// The NES background layer: 16x16 attribute areas, each of which uses one of four sub-palettes of three
+    /// colours alongside the shared backdrop colour.
+    ///
+    /// Meant for 256x240 images.
+    pub fn nes() -> Self {
+        Self::new(palettes::NES.to_vec(), (16, 16), 4)
+            .with_background(Background::Best)
+            .with_subsets(4)
+    }
+
+    /// Creates a clone of the layout where every pixel is `pixel_width` image pixels wide - such as `2`
+    /// for the double-wide pixels of the C64 multicolour mode.
+    ///
+    /// The cell size is in these wider pixels. Defaults to `1`.
+    pub fn with_pixel_width(&self, pixel_width: usize) -> Self {
+        Self { pixel_width: pixel_width.max(1), ..self.clone() }
+    }
+
+    /// Creates a clone of the layout with a different background. The background counts towards the colours
+    /// of each cell.
+    pub fn with_background(&self, background: Background) -> Self {
+        Self { background, ..self.clone() }
+    }
+
+    /// Creates a clone of the layout where the colours of each cell must all come from one of the groups of
+    /// palette indices - such as the normal and `BRIGHT` halves of the ZX Spectrum palette.
+    ///
+    /// Without any groups, each cell can use the entire palette.
+    pub fn with_groups(&self, groups: Vec<Vec<usize>>) -> Self {
+        Self { groups, ..self.clone() }
+    }
+
+    /// Creates a clone of the layout where every cell has to use one of `count` subsets of colours, which are
+    /// shared across the image - such as the four sub-palettes of the NES.
+    pub fn with_subsets(&self, count: usize) -> Self {
+        Self { subsets: Some(count.max(1)), ..self.clone() }
+    }
+
+    /// The palette that the colours of every cell are picked from.
+    pub fn palette(&self) -> &[Srgb] {
+        &self.palette
+    }
+
+    /// The size of each cell as `(width, height)`, in pixels which are `pixel_width` image pixels wide.
+    pub fn cell_size(&self) -> (usize, usize) {
+        self.cell_size
+    }
+
+    /// How many image pixels wide each pixel is.
+    pub fn pixel_width(&self) -> usize {
+        self.pixel_width
+    }
+
+    /// How many colours each cell can use, including the background.
+    pub fn colours(&self) -> usize {
+        self.colours
+    }
+
+    /// The colour shared by every cell.
+    pub fn background(&self) -> Background {
+        self.background
+    }
+}
+
+/// Represents dithering with _attribute clash_, following the colour constraints of an [`AttributeLayout`].
+///
+/// First, the subset of colours whose mixes best reproduce each cell is picked - trying every allowed subset
+/// where feasible, and otherwise building one up greedily and improving it by swapping single colours.
+/// The image is then dithered with error propagation, where each pixel can only use the colours of its cell.
+#[derive(Clone)]
+pub struct AttributeDither<'a, 'b> {
+    layout: AttributeLayout,
+    propagator: ErrorPropagator<'a, 'b, Base>,
+}
+
+impl<'a, 'b> AttributeDither<'a, 'b> {
+    /// Creates a new `AttributeDither`, which uses Floyd-Steinberg error propagation with an error limit of `0.25`.
+    ///
+    /// Error that the colours of a cell can't absorb would otherwise keep building up, and bleed far into
+    /// the neighbouring cells.
+    pub fn new(layout: AttributeLayout) -> Self {
+        Self { layout, propagator: FLOYD_STEINBERG.with_error_limit(0.25) }
+    }
+
+    /// Creates a clone of the ditherer with a different error propagator, such as `ATKINSON` - along with
+    /// its colour distance, colour space and every other setting.
+    ///
+    /// The colours of each cell are picked in the colour space of the propagator, since that's where their
+    /// mixes are formed. Exact matches are never preserved, as they may not be available to their cell.
+    pub fn with_propagator(&self, propagator: ErrorPropagator<'a, 'b, Base>) -> Self {
+        Self { layout: self.layout.clone(), propagator }
+    }
+
+    /// The layout the ditherer follows.
+    pub fn layout(&self) -> &AttributeLayout {
+        &self.layout
+    }
+
+    /// Dithers the image, returning the colours picked for each cell alongside the palette index of every pixel.
+    ///
+    /// Fails if the palette of the layout is empty - applying the ditherer as an effect returns such images
+    /// unchanged instead.
+    pub fn dither_cells(&self, image: RgbImageRepr) -> Result<AttributeImage, IndexedError> {
+        if self.layout.palette.is_empty() {
+            return Err(IndexedError::EmptyPalette);
+        }
+
+        Ok(self.dither(image))
+    }
+
+    /// Dithers the image into cells, with a palette that isn't empty.
+    fn dither(&self, image: RgbImageRepr) -> AttributeImage {
+        let layout = &self.layout;
+
+        let image = narrow(&image, layout.pixel_width);
+        let (width, height) = get_dimensions_of_matrix(&image);
+        let (cell_width, cell_height) = layout.cell_size;
+        let columns = width.div_ceil(cell_width);
+        let rows = height.div_ceil(cell_height);
+
+        let propagator = self.propagator
+            .with_preserve_exact_matches(false)
+            .with_palette(layout.palette.clone());
+        let matcher = propagator.palette_matcher();
+        let space = propagator.colour_space();
+        let palette_in_space: Vec<_> = layout.palette.iter().map(|&colour| space.from_srgb(colour)).collect();
+
+        let background = match layout.background {
+            Background::None => None,
+            Background::Fixed(index) => Some(index.min(layout.palette.len() - 1)),
+            Background::Best => {
+                let mut counts = vec![0usize; layout.palette.len()];
+                for pixel in image.iter().flatten() {
+                    counts[matcher.nearest_index(Srgb::from(*pixel).into_format())] += 1;
+                }
+
+                // the first of the most common colours
+                counts.iter().enumerate().rev().max_by_key(|(_, count)| **count).map(|(index, _)| index)
+            },
+        };
+
+        let cells: Vec<CellColours> = (0..rows * columns)
+            .map(|cell| {
+                let (x, y) = ((cell % columns) * cell_width, (cell / columns) * cell_height);
+                let pixels = image[y..(y + cell_height).min(height)].iter()
+                    .flat_map(|row| row[x..(x + cell_width).min(width)].iter().copied());
+
+                CellColours::new(pixels, &palette_in_space, space)
+            })
+            .collect();
+
+        let size = layout.colours - background.is_some() as usize;
+        let groups: Vec<Vec<usize>> = match layout.groups.is_empty() {
+            true => vec![(0..layout.palette.len()).collect()],
+            false => layout.groups.iter()
+                .map(|group| group.iter().copied().filter(|&index| index < layout.palette.len()).collect())
+                .collect(),
+        };
+        // picking the background again would only waste a colour
+        let groups: Vec<Vec<usize>> = groups.into_iter()
+            .map(|group| match group.iter().filter(|&&index| Some(index) != background).count() >= size {
+                true => group.into_iter().filter(|&index| Some(index) != background).collect(),
+                false => group,
+            })
+            .filter(|group| !group.is_empty())
+            .collect();
+
+        let subsets = match layout.subsets {
+            Some(count) => shared_subsets(&cells, &groups, size, background, count),
+            None => cells.iter().map(|cell| best_subset_of_groups(cell, &groups, size, background)).collect(),
+        };
+
+        let candidates: Vec<Vec<usize>> = subsets.iter()
+            .map(|subset| subset.iter().copied().chain(background).collect())
+            .collect();
+        let guide = CellGuide { cell_size: layout.cell_size, columns, candidates: &candidates };
+        let (_, indices) = propagator.propagate(image, None, &guide);
+
+        AttributeImage {
+            width,
+            height,
+            pixel_width: layout.pixel_width,
+            cell_size: layout.cell_size,
+            palette: layout.palette.clone(),
+            background,
+            cells: subsets,
+            indices: indices.into_iter().flatten().collect(),
+        }
+    }
+}
+
+impl<'a, 'b> Effect<RgbImageRepr> for AttributeDither<'a, 'b> {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        if self.layout.palette.is_empty() {
+            return image;
+        }
+
+        let (width, _) = get_dimensions_of_matrix(&image);
+
+        self.dither(image).to_rgb().into_iter()
+            .map(|mut row| {
+                row.truncate(width);
+                row
+            })
+            .collect()
+    }
+}
+
+impl<'a, 'b> IndexedDither for AttributeDither<'a, 'b> {
+    fn palette(&self) -> Vec<Srgb> {
+        self.layout.palette.clone()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        let (width, height) = get_dimensions_of_matrix(&image);
+
+        if self.layout.palette.is_empty() {
+            return vec![vec![0; width]; height];
+        }
+
+        let dithered = self.dither(image);
+
+        dithered.indices.chunks(dithered.width.max(1))
+            .map(|row| {
+                row.iter()
+                    .flat_map(|index| std::iter::repeat_n(*index, dithered.pixel_width))
+                    .take(width)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// An image dithered with attribute clash: the colours picked for each cell, alongside the palette index of
+/// every pixel - as needed by the native formats of retro hardware.
+///
+/// The dimensions are in pixels of the layout, which may be wider than image pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeImage {
+    width: usize,
+    height: usize,
+    pixel_width: usize,
+    cell_size: (usize, usize),
+    palette: Vec<Srgb>,
+    background: Option<usize>,
+    cells: Vec<Vec<usize>>,
+    indices: Vec<usize>,
+}
+
+impl AttributeImage {
+    /// The width and height of the image, in pixels of the layout.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// How many image pixels wide each pixel is.
+    pub fn pixel_width(&self) -> usize {
+        self.pixel_width
+    }
+
+    /// The size of each cell as `(width, height)`.
+    pub fn cell_size(&self) -> (usize, usize) {
+        self.cell_size
+    }
+
+    /// The palette the indices refer to.
+    pub fn palette(&self) -> &[Srgb] {
+        &self.palette
+    }
+
+    /// The palette index of the colour shared by every cell, if any.
+    pub fn background(&self) -> Option<usize> {
+        self.background
+    }
+
+    /// The palette indices picked for every cell, row by row - not including the background.
+    pub fn cells(&self) -> &[Vec<usize>] {
+        &self.cells
+    }
+
+    /// The palette indices picked for the cell containing the given pixel - not including the background.
+    pub fn cell(&self, x: usize, y: usize) -> &[usize] {
+        let columns = self.width.div_ceil(self.cell_size.0);
+        &self.cells[(y / self.cell_size.1) * columns + x / self.cell_size.0]
+    }
+
+    /// The palette index of every pixel, row by row.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// The palette index of a single pixel.
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        self.indices[y * self.width + x]
+    }
+
+    /// Converts the image back into RGB pixels, repeating each pixel to its full width.
+    pub fn to_rgb(&self) -> RgbImageRepr {
+        let palette: Vec<[u8; 3]> = self.palette.iter().map(|colour| colour.into_format().into()).collect();
+
+        self.indices.chunks(self.width.max(1))
+            .map(|row| {
+                row.iter()
+                    .flat_map(|index| std::iter::repeat_n(palette[*index], self.pixel_width))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Encodes the image as a ZX Spectrum screen dump (`.scr`) - 6144 bytes of bitmap in the interleaved order of
+    /// the display file, followed by 768 bytes of attributes.
+    ///
+    /// The first colour of each cell becomes its ink, and the second its paper. The image must be 256x192,
+    /// dithered with [`AttributeLayout::zx_spectrum`].
+    pub fn write_scr<W: Write>(&self, mut writer: W) -> ImageResult<()> {
+        let supported = self.width == 256 && self.height == 192 && self.pixel_width == 1
+            && self.cell_size == (8, 8) && self.background.is_none()
+            && self.palette == *palettes::ZX_SPECTRUM
+            && self.cells.iter().all(|cell| cell.len() == 2);
+
+        if !supported {
+            return Err(unsupported("SCR", "only 256x192 images using the ZX Spectrum layout can be encoded"));
+        }
+
+        let mut bytes = vec![0u8; 6144 + 768];
+
+        for y in 0..192 {
+            for column in 0..32 {
+                let ink = self.cell(column * 8, y)[0];
+                let byte = (0..8).fold(0, |byte, i| match self.index(column * 8 + i, y) == ink {
+                    true => byte | 0x80 >> i,
+                    false => byte,
+                });
+
+                let address = ((y & 0xC0) << 5) | ((y & 0x07) << 8) | ((y & 0x38) << 2) | column;
+                bytes[address] = byte;
+            }
+        }
+
+        for (i, cell) in self.cells.iter().enumerate() {
+            // black is the same either way, so it can be paired with both normal and bright colours
+            let bright = cell.iter().any(|&index| index > 8);
+            if bright && cell.iter().any(|&index| (1..8).contains(&index)) {
+                return Err(unsupported("SCR", "a cell can't mix normal and bright colours"));
+            }
+
+            let (ink, paper) = (cell[0] % 8, cell[1] % 8);
+            bytes[6144 + i] = (bright as u8) << 6 | (paper as u8) << 3 | ink as u8;
+        }
+
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Saves the image as a ZX Spectrum screen dump. See [`AttributeImage::write_scr`].
+    pub fn save_scr<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.write_scr(BufWriter::new(File::create(path)?))
+    }
+
+    /// Encodes the image as a Koala Painter file (`.kla`) for the Commodore 64 - the load address, 8000 bytes of
+    /// bitmap, 1000 bytes of screen memory, 1000 bytes of colour memory and the background colour.
+    ///
+    /// The colours of each cell go into the upper and lower half of its screen memory and its colour memory,
+    /// in that order. The image must be 320x200, dithered with [`AttributeLayout::c64_multicolour`].
+    pub fn write_koala<W: Write>(&self, mut writer: W) -> ImageResult<()> {
+        let supported = self.width == 160 && self.height == 200 && self.pixel_width == 2
+            && self.cell_size == (4, 8) && self.palette == *palettes::C64
+            && self.cells.iter().all(|cell| cell.len() == 3);
+
+        let Some(background) = self.background.filter(|_| supported) else {
+            return Err(unsupported("KLA", "only 320x200 images using the C64 multicolour layout can be encoded"));
+        };
+
+        let mut bytes = vec![0x00, 0x60];
+
+        for cell in 0..1000 {
+            let (x, y) = ((cell % 40) * 4, (cell / 40) * 8);
+            let colours = self.cell(x, y);
+
+            for line in y..y + 8 {
+                bytes.push((0..4).fold(0, |byte, i| {
+                    let index = self.index(x + i, line);
+                    let bits = match index == background {
+                        true => 0,
+                        false => colours.iter().position(|&colour| colour == index).map_or(0, |position| position + 1),
+                    };
+
+                    byte | (bits as u8) << (6 - 2 * i)
+                }));
+            }
+        }
+
+        bytes.extend(self.cells.iter().map(|cell| (cell[0] as u8) << 4 | cell[1] as u8));
+        bytes.extend(self.cells.iter().map(|cell| cell[2] as u8));
+        bytes.push(background as u8);
+
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Saves the image as a Koala Painter file. See [`AttributeImage::write_koala`].
+    pub fn save_koala<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.write_koala(BufWriter::new(File::create(path)?))
+    }
+}
+
+fn unsupported(format: &str, reason: &str) -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormatHint::Name(format.to_string()),
+        UnsupportedErrorKind::GenericFeature(reason.to_string()),
+    ))
+}
+
+/// Averages every `pixel_width` pixels of each row into one.
+fn narrow(image: &RgbImageRepr, pixel_width: usize) -> RgbImageRepr {
+    image.iter()
+        .map(|row| {
+            row.chunks(pixel_width)
+                .map(|pixels| {
+                    [0, 1, 2].map(|channel| {
+                        let total: usize = pixels.iter().map(|pixel| pixel[channel] as usize).sum();
+                        ((total as f32 / pixels.len() as f32).round()) as u8
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Restricts each pixel to the colours of its cell.
+struct CellGuide<'c> {
+    cell_size: (usize, usize),
+    columns: usize,
+    candidates: &'c [Vec<usize>],
+}
+
+impl<'c> PixelGuide for CellGuide<'c> {
+    fn candidates(&self, x: usize, y: usize) -> Option<&[usize]> {
+        Some(&self.candidates[(y / self.cell_size.1) * self.columns + x / self.cell_size.0])
+    }
+}
+
+/// The distinct colours of one or more cells in the colour space, weighted by how many pixels have them.
+struct CellColours<'p> {
+    palette: &'p [(f32, f32, f32)],
+    weights: Vec<f32>,
+    colours: Vec<(f32, f32, f32)>,
+}
+
+impl<'p> CellColours<'p> {
+    fn new(pixels: impl Iterator<Item = [u8; 3]>, palette: &'p [(f32, f32, f32)], space: ColourSpace) -> Self {
+        let mut counts: BTreeMap<[u8; 3], usize> = BTreeMap::new();
+        for pixel in pixels {
+            *counts.entry(pixel).or_default() += 1;
+        }
+
+        let (weights, colours) = counts.into_iter()
+            .map(|(pixel, count)| (count as f32, space.from_srgb(Srgb::from(pixel).into_format())))
+            .unzip();
+
+        Self { palette, weights, colours }
+    }
+
+    fn merge(cells: &[&CellColours<'p>]) -> Self {
+        Self {
+            palette: cells.first().map_or(&[], |cell| cell.palette),
+            weights: cells.iter().flat_map(|cell| cell.weights.iter().copied()).collect(),
+            colours: cells.iter().flat_map(|cell| cell.colours.iter().copied()).collect(),
+        }
+    }
+
+    /// How badly the subset and the background reproduce the pixels - as the total distance from every pixel
+    /// to the closest mix of two of the colours, since that's what dithering can produce.
+    ///
+    /// The distance to the closest single colour is added as well, at a lower weight, so that fewer pixels
+    /// need to be dithered.
+    fn cost(&self, subset: &[usize], background: Option<usize>) -> f32 {
+        let colours = || subset.iter().chain(&background).map(|&index| self.palette[index]);
+
+        self.weights.iter().zip(&self.colours)
+            .map(|(weight, &pixel)| {
+                let nearest = colours().map(|colour| distance(pixel, colour)).fold(f32::MAX, f32::min);
+                let mixed = colours().enumerate()
+                    .flat_map(|(i, a)| colours().skip(i + 1).map(move |b| distance_to_mix(pixel, a, b)))
+                    .fold(nearest, f32::min);
+
+                weight * (mixed + NEAREST_WEIGHT * nearest)
+            })
+            .sum()
+    }
+}
+
+fn distance(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// The distance from the colour to the closest mix of `a` and `b`.
+fn distance_to_mix(colour: (f32, f32, f32), a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    let ab = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+    let length = ab.0 * ab.0 + ab.1 * ab.1 + ab.2 * ab.2;
+    if length == 0.0 {
+        return distance(colour, a);
+    }
+
+    let t = (((colour.0 - a.0) * ab.0 + (colour.1 - a.1) * ab.1 + (colour.2 - a.2) * ab.2) / length).clamp(0.0, 1.0);
+    distance(colour, (a.0 + t * ab.0, a.1 + t * ab.1, a.2 + t * ab.2))
+}
+
+/// The best subset of `size` colours out of any one of the groups.
+fn best_subset_of_groups(cell: &CellColours<'_>, groups: &[Vec<usize>], size: usize, background: Option<usize>) -> Vec<usize> {
+    groups.iter()
+        .map(|group| {
+            let subset = best_subset(cell, group, size, background);
+            (cell.cost(&subset, background), subset)
+        })
+        .fold((f32::MAX, Vec::new()), |best, current| if current.0 < best.0 { current } else { best })
+        .1
+}
+
+/// The best subset of `size` colours out of the allowed ones - trying every subset if there aren't too many.
+fn best_subset(cell: &CellColours<'_>, allowed: &[usize], size: usize, background: Option<usize>) -> Vec<usize> {
+    if allowed.len() <= size {
+        let mut subset = allowed.to_vec();
+        while subset.len() < size {
+            subset.push(subset.last().copied().or(background).unwrap_or(0));
+        }
+
+        return subset;
+    }
+
+    if binomial(allowed.len(), size) > EXHAUSTIVE_LIMIT {
+        let mut subset = Vec::with_capacity(size);
+        for _ in 0..size {
+            let next = allowed.iter().copied()
+                .filter(|index| !subset.contains(index))
+                .map(|index| (cell.cost(&[subset.as_slice(), &[index]].concat(), background), index))
+                .fold((f32::MAX, allowed[0]), |best, current| if current.0 < best.0 { current } else { best });
+
+            subset.push(next.1);
+        }
+
+        return refine(cell, subset, allowed, background);
+    }
+
+    let mut positions: Vec<usize> = (0..size).collect();
+    let mut best = (f32::MAX, Vec::new());
+
+    loop {
+        let subset: Vec<usize> = positions.iter().map(|&position| allowed[position]).collect();
+        let cost = cell.cost(&subset, background);
+        if cost < best.0 {
+            best = (cost, subset);
+        }
+
+        let Some(i) = (0..size).rev().find(|&i| positions[i] < allowed.len() - size + i) else { break };
+        positions[i] += 1;
+        for j in i + 1..size {
+            positions[j] = positions[j - 1] + 1;
+        }
+    }
+
+    best.1
+}
+
+/// Improves the subset by swapping out one colour at a time for another allowed colour, until no swap helps.
+fn refine(cell: &CellColours<'_>, mut subset: Vec<usize>, allowed: &[usize], background: Option<usize>) -> Vec<usize> {
+    let mut cost = cell.cost(&subset, background);
+
+    for _ in 0..MAX_SWAP_PASSES {
+        let mut improved = false;
+
+        for i in 0..subset.len() {
+            for &index in allowed {
+                if subset.contains(&index) {
+                    continue;
+                }
+
+                let previous = std::mem::replace(&mut subset[i], index);
+                let swapped = cell.cost(&subset, background);
+
+                if swapped < cost {
+                    cost = swapped;
+                    improved = true;
+                } else {
+                    subset[i] = previous;
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    subset
+}
+
+/// Picks `count` subsets shared across the image, returning the one used by each cell.
+///
+/// The subsets start out as the best subsets of individual cells - picked one at a time, each lowering the
+/// total cost the most. Then each cell is assigned its closest subset, and each subset is re-fitted to
+/// its cells, until nothing changes.
+fn shared_subsets(
+    cells: &[CellColours<'_>],
+    groups: &[Vec<usize>],
+    size: usize,
+    background: Option<usize>,
+    count: usize,
+) -> Vec<Vec<usize>> {
+    let mut candidates: Vec<Vec<usize>> = cells.iter()
+        .map(|cell| {
+            let mut subset = best_subset_of_groups(cell, groups, size, background);
+            subset.sort_unstable();
+            subset
+        })
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let costs: Vec<Vec<f32>> = cells.iter()
+        .map(|cell| candidates.iter().map(|subset| cell.cost(subset, background)).collect())
+        .collect();
+
+    let mut closest = vec![f32::MAX; cells.len()];
+    let mut shared: Vec<Vec<usize>> = Vec::new();
+
+    for _ in 0..count.min(candidates.len()) {
+        let total = |candidate: usize| -> f32 {
+            costs.iter().zip(&closest).map(|(costs, closest)| costs[candidate].min(*closest)).sum()
+        };
+
+        let Some(best) = (0..candidates.len())
+            .filter(|candidate| !shared.contains(&candidates[*candidate]))
+            .map(|candidate| (total(candidate), candidate))
+            .fold(None, |best: Option<(f32, usize)>, current| match best {
+                Some(best) if best.0 <= current.0 => Some(best),
+                _ => Some(current),
+            })
+        else {
+            break;
+        };
+
+        for (closest, costs) in closest.iter_mut().zip(&costs) {
+            *closest = closest.min(costs[best.1]);
+        }
+        shared.push(candidates[best.1].clone());
+    }
+
+    let assign = |shared: &[Vec<usize>]| -> Vec<usize> {
+        cells.iter()
+            .map(|cell| {
+                (0..shared.len())
+                    .map(|subset| (cell.cost(&shared[subset], background), subset))
+                    .fold((f32::MAX, 0), |best, current| if current.0 < best.0 { current } else { best })
+                    .1
+            })
+            .collect()
+    };
+
+    let mut assignment = assign(&shared);
+
+    for _ in 0..MAX_REFINEMENTS {
+        let refined: Vec<Vec<usize>> = shared.iter().enumerate()
+            .map(|(subset, colours)| {
+                let assigned: Vec<&CellColours<'_>> = cells.iter().zip(&assignment)
+                    .filter(|(_, assigned)| **assigned == subset)
+                    .map(|(cell, _)| cell)
+                    .collect();
+
+                if assigned.is_empty() {
+                    return colours.clone();
+                }
+
+                let merged = CellColours::merge(&assigned);
+                groups.iter()
+                    .filter(|group| colours.iter().all(|index| group.contains(index)))
+                    .map(|group| refine(&merged, colours.clone(), group, background))
+                    .next()
+                    .unwrap_or_else(|| colours.clone())
+            })
+            .collect();
+
+        if refined == shared {
+            break;
+        }
+
+        shared = refined;
+        assignment = assign(&shared);
+    }
+
+    assignment.into_iter().map(|subset| shared[subset].clone()).collect()
+}
+
+/// The number of ways to pick `k` out of `n`, saturating instead of overflowing.
+fn binomial(n: usize, k: usize) -> usize {
+    (0..k.min(n - k))
+        .try_fold(1usize, |total, i| total.checked_mul(n - i).map(|total| total / (i + 1)))
+        .unwrap_or(usize::MAX)
+}
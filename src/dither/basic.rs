@@ -1,7 +1,7 @@
 use image::DynamicImage;
 use palette::Srgb;
 
-use crate::{colour::utils::{grayscale_rgb, quantize_rgb, compute_rgb_error}, utils::image::RgbImageRepr};
+use crate::{colour::utils::{quantize_rgb, compute_rgb_error}, utils::image::RgbImageRepr};
 
 // this function is essentially archived. kept here moreso as an example of a naive implementation
 // rather than something to actually use - due to its poor performance.
@@ -19,9 +19,6 @@ fn basic_dither(image: &mut RgbImageRepr, palette: &[Srgb]) {
             color.red = color.red + error.0;
             color.blue = color.blue + error.1;
             color.green = color.green + error.2;
-            if false {
-                color = grayscale_rgb(color);
-            }
             let quantized = quantize_rgb(color, palette);
     
             error = compute_rgb_error(color, quantized);
@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use image::{DynamicImage, ImageResult};
+use ndarray::Array2;
+use palette::Srgb;
+
+use crate::utils::random::SplitMix64;
+
+use super::ordered::{Ordered, ThresholdMatrix};
+
+impl Ordered<ThresholdMatrix> {
+    /// Creates a new `Ordered` ditherer using a blue-noise threshold map instead of a Bayer matrix, generating a
+    /// `size * size` map with the _void-and-cluster_ algorithm.
+    ///
+    /// Blue noise has no low-frequency structure, so unlike a Bayer matrix it doesn't leave a visible cross-hatch
+    /// pattern - which makes it a good fit for animations and game assets.
+    ///
+    /// The same `seed` always results in the same threshold map. Generating larger maps gets slow quickly,
+    /// so sizes above `64` are best generated once and loaded with [`Ordered::blue_noise_from_image`] afterwards.
+    pub fn blue_noise(size: usize, seed: u64, palette: Vec<Srgb>) -> Self {
+        Self::new(ThresholdMatrix::blue_noise(size, seed), palette)
+    }
+
+    /// Creates a new `Ordered` ditherer using an existing blue-noise texture as the threshold map.
+    ///
+    /// The luminance of each pixel is used as its threshold.
+    pub fn blue_noise_from_image(texture: &DynamicImage, palette: Vec<Srgb>) -> Self {
+        Self::new(ThresholdMatrix::from_image(texture), palette)
+    }
+
+    /// Creates a new `Ordered` ditherer by loading a blue-noise texture, such as a PNG, from the path.
+    ///
+    /// See [`Ordered::blue_noise_from_image`].
+    pub fn open_blue_noise<P: AsRef<Path>>(path: P, palette: Vec<Srgb>) -> ImageResult<Self> {
+        Ok(Self::blue_noise_from_image(&image::open(path)?, palette))
+    }
+}
+
+/// Generates a `size * size` blue-noise threshold map using Ulichney's void-and-cluster algorithm.
+///
+/// Each threshold is in the range `0.0..1.0`.
+pub(crate) fn void_and_cluster(size: usize, seed: u64) -> Array2<f32> {
+    let area = size * size;
+    let mut rng = SplitMix64::new(seed);
+
+    // start with a random pattern covering roughly a tenth of the area
+    let mut initial = EnergyMap::new(size);
+    let minority = (area / 10).max(1);
+    let mut placed = 0;
+    while placed < minority {
+        let index = rng.below(area);
+        if !initial.pattern[index] {
+            initial.toggle(index);
+            placed += 1;
+        }
+    }
+
+    // move the tightest cluster into the largest void until the pattern is evenly spread
+    for _ in 0..area {
+        let cluster = initial.tightest_cluster();
+        initial.toggle(cluster);
+        let void = initial.largest_void();
+        initial.toggle(void);
+
+        if cluster == void {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; area];
+
+    // phase 1: remove the tightest clusters, ranking them from the top down
+    let mut energy = initial.clone();
+    for rank in (0..minority).rev() {
+        let cluster = energy.tightest_cluster();
+        energy.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+
+    // phase 2 & 3: fill the largest voids, ranking them from the bottom up
+    let mut energy = initial;
+    for rank in minority..area {
+        let void = energy.largest_void();
+        energy.toggle(void);
+        ranks[void] = rank;
+    }
+
+    Array2::from_shape_fn((size, size), |(y, x)| {
+        (ranks[y * size + x] as f32 + 0.5) / area as f32
+    })
+}
+
+/// A binary pattern alongside its energy - the pattern filtered by a gaussian, wrapping around the edges.
+#[derive(Clone)]
+struct EnergyMap {
+    size: usize,
+    pattern: Vec<bool>,
+    energy: Vec<f32>,
+    kernel: Vec<f32>,
+}
+
+impl EnergyMap {
+    const SIGMA: f32 = 1.5;
+
+    fn new(size: usize) -> Self {
+        let wrapped = |d: usize| d.min(size - d) as f32;
+        let kernel = (0..size * size)
+            .map(|i| {
+                let (dx, dy) = (wrapped(i % size), wrapped(i / size));
+                (-(dx * dx + dy * dy) / (2.0 * Self::SIGMA * Self::SIGMA)).exp()
+            })
+            .collect();
+
+        Self {
+            size,
+            pattern: vec![false; size * size],
+            energy: vec![0.0; size * size],
+            kernel,
+        }
+    }
+
+    fn toggle(&mut self, index: usize) {
+        let sign = if self.pattern[index] { -1.0 } else { 1.0 };
+        self.pattern[index] = !self.pattern[index];
+
+        let (px, py) = (index % self.size, index / self.size);
+
+        for (i, energy) in self.energy.iter_mut().enumerate() {
+            let (x, y) = (i % self.size, i / self.size);
+            let dx = (x + self.size - px) % self.size;
+            let dy = (y + self.size - py) % self.size;
+            *energy += sign * self.kernel[dy * self.size + dx];
+        }
+    }
+
+    /// The set pixel with the highest energy.
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// The unset pixel with the lowest energy.
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, set: bool, is_better: fn(f32, f32) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+
+        for (i, energy) in self.energy.iter().enumerate() {
+            if self.pattern[i] == set && best.is_none_or(|best| is_better(*energy, self.energy[best])) {
+                best = Some(i);
+            }
+        }
+
+        best.unwrap_or(0)
+    }
+}
@@ -0,0 +1,282 @@
+use palette::Srgb;
+
+use crate::{
+    utils::image::{get_dimensions_of_matrix, RgbImageRepr},
+    colour::{utils::PaletteMatcher, comparisons::ColourDistance, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::indexed::IndexedDither;
+
+type Colour = (f32, f32, f32);
+
+/// Represents _direct binary search_ (DBS) halftoning.
+///
+/// Starting from the output of another ditherer, it repeatedly visits every pixel and tries changing it to
+/// every other palette colour, as well as swapping it with each of its neighbours - keeping whichever change
+/// reduces the difference between the original and the dithered image the most, as perceived by the eye.
+/// The eye is modelled as a gaussian blur, so the difference is measured between the blurred images.
+///
+/// This is by far the slowest ditherer, but also the one with the highest quality - which makes it
+/// best suited for images that only need to be dithered once, such as print masters.
+#[derive(Clone)]
+pub struct DirectBinarySearch<E: Effect<RgbImageRepr>> {
+    initial: E,
+    palette: Vec<Srgb>,
+    max_iterations: usize,
+    convergence: f32,
+    sigma: f32,
+    distance: ColourDistance,
+    colour_space: ColourSpace,
+}
+
+impl<E: Effect<RgbImageRepr>> DirectBinarySearch<E> {
+    /// Creates a new `DirectBinarySearch` ditherer, refining the output of the `initial` ditherer - for example
+    /// `Bayer::new(8, palette.clone())` or `FLOYD_STEINBERG.with_palette(palette.clone())`.
+    ///
+    /// The palette should be the same one the initial ditherer uses. Any colours of its output outside
+    /// of the palette get replaced with the closest palette colour first.
+    pub fn new(initial: E, palette: Vec<Srgb>) -> Self {
+        Self {
+            initial,
+            palette,
+            max_iterations: 16,
+            convergence: 0.0,
+            sigma: 1.2,
+            distance: ColourDistance::WeightedRgb,
+            colour_space: ColourSpace::Srgb,
+        }
+    }
+
+    /// Creates a clone of the ditherer with a different limit on the amount of passes over the image.
+    /// Defaults to `16`.
+    pub fn with_max_iterations(&self, max_iterations: usize) -> Self where E: Clone {
+        Self { max_iterations, initial: self.initial.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that stops once a pass changes less than the given fraction of pixels.
+    ///
+    /// Defaults to `0.0`, which only stops once a pass changes nothing at all - or the iteration limit is reached.
+    pub fn with_convergence(&self, convergence: f32) -> Self where E: Clone {
+        Self { convergence, initial: self.initial.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different radius for the blur modelling the eye, in pixels.
+    ///
+    /// Larger values correspond to viewing the image from further away. Defaults to `1.2`.
+    pub fn with_sigma(&self, sigma: f32) -> Self where E: Clone {
+        Self { sigma: sigma.max(0.1), initial: self.initial.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different colour distance metric, used to map the initial output
+    /// onto the palette.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self where E: Clone {
+        Self { distance, initial: self.initial.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that measures the difference in a different colour space.
+    ///
+    /// Since the eye mixes light rather than sRGB values, `ColourSpace::LinearRgb` usually gives the most accurate tones.
+    pub fn with_colour_space(&self, colour_space: ColourSpace) -> Self where E: Clone {
+        Self { colour_space, initial: self.initial.clone(), palette: self.palette.clone(), ..*self }
+    }
+}
+
+/// Tracks the correlation between the error of the halftone and the autocorrelation of the eye's blur,
+/// which is all that's needed to tell how much a change to a single pixel affects the perceived error.
+struct ErrorCorrelation {
+    xdim: usize,
+    ydim: usize,
+    radius: usize,
+    autocorrelation: Vec<f32>,
+    correlation: Vec<Colour>,
+}
+
+impl ErrorCorrelation {
+    fn new(error: &[Colour], xdim: usize, ydim: usize, sigma: f32) -> Self {
+        // the autocorrelation of a gaussian is another gaussian, wider by a factor of sqrt(2)
+        let sigma = sigma * std::f32::consts::SQRT_2;
+        let radius = (3.0 * sigma).ceil() as usize;
+        let size = 2 * radius + 1;
+
+        let autocorrelation = (0..size * size)
+            .map(|i| {
+                let (dx, dy) = ((i % size) as f32 - radius as f32, (i / size) as f32 - radius as f32);
+                (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+
+        let mut correlation = Self {
+            xdim,
+            ydim,
+            radius,
+            autocorrelation,
+            correlation: vec![(0.0, 0.0, 0.0); xdim * ydim],
+        };
+
+        for (i, error) in error.iter().enumerate() {
+            correlation.add(i % xdim, i / xdim, *error);
+        }
+
+        correlation
+    }
+
+    /// The autocorrelation at the offset between two pixels.
+    fn at(&self, dx: i64, dy: i64) -> f32 {
+        let r = self.radius as i64;
+        if dx.abs() > r || dy.abs() > r {
+            return 0.0;
+        }
+        self.autocorrelation[((dy + r) * (2 * r + 1) + dx + r) as usize]
+    }
+
+    fn get(&self, x: usize, y: usize) -> Colour {
+        self.correlation[y * self.xdim + x]
+    }
+
+    /// Accounts for the error at a pixel changing by `change`.
+    fn add(&mut self, x: usize, y: usize, change: Colour) {
+        let r = self.radius;
+
+        for ny in y.saturating_sub(r)..(y + r + 1).min(self.ydim) {
+            for nx in x.saturating_sub(r)..(x + r + 1).min(self.xdim) {
+                let weight = self.at(nx as i64 - x as i64, ny as i64 - y as i64);
+                let correlation = &mut self.correlation[ny * self.xdim + nx];
+                correlation.0 += change.0 * weight;
+                correlation.1 += change.1 * weight;
+                correlation.2 += change.2 * weight;
+            }
+        }
+    }
+}
+
+fn dot(a: Colour, b: Colour) -> f32 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+fn difference(a: Colour, b: Colour) -> Colour {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn negate(a: Colour) -> Colour {
+    (-a.0, -a.1, -a.2)
+}
+
+impl<E: Effect<RgbImageRepr>> Effect<RgbImageRepr> for DirectBinarySearch<E> {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        if self.palette.is_empty() {
+            return image;
+        }
+
+        self.search(image).into_iter()
+            .map(|row| row.into_iter().map(|index| self.palette[index].into_format().into()).collect())
+            .collect()
+    }
+}
+
+impl<E: Effect<RgbImageRepr>> IndexedDither for DirectBinarySearch<E> {
+    fn palette(&self) -> Vec<Srgb> {
+        self.palette.clone()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.search(image)
+    }
+}
+
+impl<E: Effect<RgbImageRepr>> DirectBinarySearch<E> {
+    /// Refines the output of the initial ditherer, returning the palette index picked for each pixel.
+    fn search(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        let (xdim, ydim) = get_dimensions_of_matrix(&image);
+
+        if xdim == 0 || ydim == 0 || self.palette.is_empty() {
+            return vec![vec![0; xdim]; ydim];
+        }
+
+        let space = self.colour_space;
+        let matcher = PaletteMatcher::new(&self.palette, self.distance);
+        let palette: Vec<_> = self.palette.iter().map(|&colour| space.from_srgb(colour)).collect();
+
+        let original: Vec<Colour> = image.iter().flatten()
+            .map(|pixel| space.from_srgb(Srgb::from(*pixel).into_format()))
+            .collect();
+
+        let mut indices: Vec<usize> = self.initial.affect(image).iter().flatten()
+            .map(|pixel| matcher.nearest_index(Srgb::from(*pixel).into_format()))
+            .collect();
+
+        let error: Vec<Colour> = original.iter().zip(indices.iter())
+            .map(|(colour, index)| difference(*colour, palette[*index]))
+            .collect();
+
+        let mut correlation = ErrorCorrelation::new(&error, xdim, ydim, self.sigma);
+        let centre = correlation.at(0, 0);
+
+        for _ in 0..self.max_iterations {
+            let mut changed = 0;
+
+            for y in 0..ydim {
+                for x in 0..xdim {
+                    let current = indices[y * xdim + x];
+                    let c = correlation.get(x, y);
+
+                    // the best change so far, as (change in perceived error, new colour, swapped neighbour)
+                    let mut best: (f32, usize, Option<(usize, usize)>) = (0.0, current, None);
+
+                    for (index, colour) in palette.iter().enumerate() {
+                        let change = difference(*colour, palette[current]);
+                        let delta = centre * dot(change, change) - 2.0 * dot(change, c);
+
+                        if delta < best.0 {
+                            best = (delta, index, None);
+                        }
+                    }
+
+                    for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        if nx < 0 || ny < 0 || nx >= xdim as i64 || ny >= ydim as i64 {
+                            continue;
+                        }
+
+                        let (nx, ny) = (nx as usize, ny as usize);
+                        let neighbour = indices[ny * xdim + nx];
+                        if neighbour == current {
+                            continue;
+                        }
+
+                        let change = difference(palette[neighbour], palette[current]);
+                        let delta = 2.0 * (centre - correlation.at(dx, dy)) * dot(change, change)
+                            - 2.0 * dot(change, difference(c, correlation.get(nx, ny)));
+
+                        if delta < best.0 {
+                            best = (delta, neighbour, Some((nx, ny)));
+                        }
+                    }
+
+                    let (_, index, swapped) = best;
+                    if index == current {
+                        continue;
+                    }
+
+                    // the error changes in the opposite direction of the halftone
+                    let change = difference(palette[index], palette[current]);
+                    indices[y * xdim + x] = index;
+                    correlation.add(x, y, negate(change));
+
+                    if let Some((nx, ny)) = swapped {
+                        indices[ny * xdim + nx] = current;
+                        correlation.add(nx, ny, change);
+                    }
+
+                    changed += 1;
+                }
+            }
+
+            if changed == 0 || (changed as f32) < self.convergence * (xdim * ydim) as f32 {
+                break;
+            }
+        }
+
+        indices.chunks(xdim).map(<[usize]>::to_vec).collect()
+    }
+}
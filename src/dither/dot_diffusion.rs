@@ -0,0 +1,188 @@
+use ndarray::Array2;
+use palette::Srgb;
+
+use crate::{
+    utils::image::{get_dimensions_of_matrix, RgbImageRepr},
+    colour::{utils::PaletteMatcher, comparisons::ColourDistance, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::{alpha::MaskedDither, indexed::IndexedDither};
+
+type Colour = (f32, f32, f32);
+
+/// The `8x8` class matrix from Knuth's original paper.
+const KNUTH_CLASS_MATRIX: [[u32; 8]; 8] = [
+    [34, 48, 40, 32, 29, 15, 23, 31],
+    [42, 58, 56, 53, 21,  5,  7, 10],
+    [50, 62, 61, 45, 13,  1,  2, 18],
+    [38, 46, 54, 37, 25, 17,  9, 26],
+    [28, 14, 22, 30, 35, 49, 41, 33],
+    [20,  4,  6, 11, 43, 59, 57, 52],
+    [12,  0,  3, 19, 51, 63, 60, 44],
+    [24, 16,  8, 27, 39, 47, 55, 36],
+];
+
+/// Represents Donald Knuth's _dot diffusion_.
+///
+/// The image is split into tiles, and a _class matrix_ decides the order in which the pixels of each tile get
+/// processed. Error is only diffused to the neighbours with a higher class - those that haven't been processed
+/// yet - with orthogonal neighbours receiving twice as much as diagonal ones.
+///
+/// Since pixels of the same class never affect each other, every tile can be processed at the same time,
+/// unlike the error propagation algorithms. The result looks somewhere between those and `Bayer`.
+#[derive(Clone)]
+pub struct DotDiffusion {
+    classes: Array2<u32>,
+    palette: Vec<Srgb>,
+    distance: ColourDistance,
+    colour_space: ColourSpace,
+}
+
+impl DotDiffusion {
+    /// Creates a new `DotDiffusion` ditherer, using the `8x8` class matrix from Knuth's paper.
+    pub fn new(palette: Vec<Srgb>) -> Self {
+        Self {
+            classes: Array2::from_shape_fn((8, 8), |(y, x)| KNUTH_CLASS_MATRIX[y][x]),
+            palette,
+            distance: ColourDistance::WeightedRgb,
+            colour_space: ColourSpace::Srgb,
+        }
+    }
+
+    /// Creates a clone of the ditherer with a different class matrix, with each row being a list of classes.
+    ///
+    /// Pixels are processed from the lowest class to the highest. Rows shorter than the first row are padded with `0`,
+    /// and an empty matrix falls back to a single class - which quantizes every pixel without diffusing any error.
+    pub fn with_class_matrix(&self, classes: Vec<Vec<u32>>) -> Self {
+        let ydim = classes.len();
+        let xdim = classes.first().map(|row| row.len()).unwrap_or(0);
+
+        let classes = if xdim == 0 || ydim == 0 {
+            Array2::zeros((1, 1))
+        } else {
+            Array2::from_shape_fn((ydim, xdim), |(y, x)| classes[y].get(x).copied().unwrap_or(0))
+        };
+
+        Self { classes, palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different colour distance metric.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self {
+        Self { distance, classes: self.classes.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that diffuses error in a different colour space.
+    pub fn with_colour_space(&self, colour_space: ColourSpace) -> Self {
+        Self { colour_space, classes: self.classes.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    fn class(&self, x: usize, y: usize) -> u32 {
+        let (ydim, xdim) = self.classes.dim();
+        self.classes[(y % ydim, x % xdim)]
+    }
+}
+
+impl Effect<RgbImageRepr> for DotDiffusion {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        self.diffuse(image, None).0
+    }
+}
+
+impl IndexedDither for DotDiffusion {
+    fn palette(&self) -> Vec<Srgb> {
+        self.palette.clone()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.diffuse(image, None).1
+    }
+}
+
+impl MaskedDither for DotDiffusion {
+    fn dither_masked(&self, image: RgbImageRepr, mask: &[Vec<bool>]) -> RgbImageRepr {
+        self.diffuse(image, Some(mask)).0
+    }
+}
+
+impl DotDiffusion {
+    /// Dithers the image, skipping the pixels that are excluded by the mask - they neither receive nor diffuse error.
+    ///
+    /// Returns the palette index picked for each pixel alongside the image, with excluded pixels getting `0`.
+    fn diffuse(&self, mut image: RgbImageRepr, mask: Option<&[Vec<bool>]>) -> (RgbImageRepr, Vec<Vec<usize>>) {
+        let (xdim, ydim) = get_dimensions_of_matrix(&image);
+        let mut indices = vec![vec![0; xdim]; ydim];
+
+        if xdim == 0 || ydim == 0 || self.palette.is_empty() {
+            return (image, indices);
+        }
+
+        let space = self.colour_space;
+        let palette = PaletteMatcher::new(&self.palette, self.distance);
+        let palette_in_space: Vec<_> = self.palette.iter().map(|&colour| space.from_srgb(colour)).collect();
+
+        let mut buffer: Vec<Vec<Colour>> = image.iter()
+            .map(|row| row.iter().map(|pixel| space.from_srgb(Srgb::from(*pixel).into_format())).collect())
+            .collect();
+
+        let is_excluded = |x: usize, y: usize| mask.is_some_and(|mask| !mask[y][x]);
+
+        // every position of a tile, from the lowest class to the highest
+        let (tile_ydim, tile_xdim) = self.classes.dim();
+        let mut positions: Vec<(usize, usize)> = (0..tile_ydim)
+            .flat_map(|y| (0..tile_xdim).map(move |x| (x, y)))
+            .collect();
+        positions.sort_by_key(|&(x, y)| self.classes[(y, x)]);
+
+        for (tx, ty) in positions {
+            for y in (ty..ydim).step_by(tile_ydim) {
+                for x in (tx..xdim).step_by(tile_xdim) {
+                    if is_excluded(x, y) {
+                        continue;
+                    }
+
+                    let colour = buffer[y][x];
+                    let index = palette.nearest_index_in(colour, space);
+                    let quantized = palette_in_space[index];
+
+                    image[y][x] = self.palette[index].into_format().into();
+                    indices[y][x] = index;
+
+                    let class = self.class(x, y);
+                    let mut neighbours = Vec::with_capacity(8);
+
+                    for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        if nx < 0 || ny < 0 || nx >= xdim as i64 || ny >= ydim as i64 {
+                            continue;
+                        }
+
+                        let (nx, ny) = (nx as usize, ny as usize);
+                        if self.class(nx, ny) > class && !is_excluded(nx, ny) {
+                            let weight = if dx == 0 || dy == 0 { 2.0 } else { 1.0 };
+                            neighbours.push((nx, ny, weight));
+                        }
+                    }
+
+                    // pixels with no unprocessed neighbours simply drop their error
+                    let total: f32 = neighbours.iter().map(|(_, _, weight)| weight).sum();
+                    if total == 0.0 {
+                        continue;
+                    }
+
+                    let error = (colour.0 - quantized.0, colour.1 - quantized.1, colour.2 - quantized.2);
+
+                    for (nx, ny, weight) in neighbours {
+                        let share = weight / total;
+                        let neighbour = &mut buffer[ny][nx];
+                        neighbour.0 += error.0 * share;
+                        neighbour.1 += error.1 * share;
+                        neighbour.2 += error.2 * share;
+                    }
+                }
+            }
+        }
+
+        (image, indices)
+    }
+}
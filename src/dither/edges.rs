@@ -0,0 +1,150 @@
+use palette::Srgb;
+
+use crate::{
+    utils::image::{get_dimensions_of_matrix, RgbImageRepr},
+    colour::conversions::{rec_709_luminance, ColourSpace},
+};
+
+/// How edges are detected, so that error propagation can avoid crossing them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeDetection {
+    /// Detects edges using the Sobel gradient of the luminance of the image.
+    ///
+    /// Error propagated across an edge - between pixels on its darker and lighter side - gets attenuated, while
+    /// error moving along the edge doesn't. A pixel is on an edge when its gradient magnitude exceeds the threshold,
+    /// relative to a hard edge between black and white, which has a magnitude of `1.0`.
+    Sobel(f32),
+
+    /// Detects edges by comparing the original colours of two pixels, in the configured colour space.
+    ///
+    /// Error propagated between two pixels whose largest per-channel difference exceeds the threshold gets
+    /// attenuated. For sRGB, `1.0` spans the entire channel.
+    Difference(f32),
+}
+
+/// Edge detection alongside how much of the error makes it across an edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct EdgeAware {
+    pub detection: EdgeDetection,
+    pub attenuation: f32,
+}
+
+/// The edges of an image, prepared for deciding how much error makes it from one pixel to another.
+pub(crate) enum EdgeMap {
+    Gradient {
+        width: usize,
+        luminance: Vec<f32>,
+        magnitudes: Vec<f32>,
+        midpoints: Vec<f32>,
+        threshold: f32,
+        attenuation: f32,
+    },
+    Difference { width: usize, colours: Vec<(f32, f32, f32)>, threshold: f32, attenuation: f32 },
+}
+
+impl EdgeMap {
+    pub fn new(image: &RgbImageRepr, edges: EdgeAware, space: ColourSpace) -> Self {
+        let (width, height) = get_dimensions_of_matrix(image);
+        let EdgeAware { detection, attenuation } = edges;
+
+        match detection {
+            EdgeDetection::Sobel(threshold) => {
+                let luminance: Vec<f32> = image.iter().flatten()
+                    .map(|pixel| rec_709_luminance(Srgb::from(*pixel).into_format()))
+                    .collect();
+                let luma = |x: i64, y: i64| {
+                    luminance[y.clamp(0, height as i64 - 1) as usize * width + x.clamp(0, width as i64 - 1) as usize]
+                };
+
+                let magnitudes = (0..width * height)
+                    .map(|i| {
+                        let (x, y) = ((i % width) as i64, (i / width) as i64);
+
+                        let gx = luma(x + 1, y - 1) + 2.0 * luma(x + 1, y) + luma(x + 1, y + 1)
+                            - luma(x - 1, y - 1) - 2.0 * luma(x - 1, y) - luma(x - 1, y + 1);
+                        let gy = luma(x - 1, y + 1) + 2.0 * luma(x, y + 1) + luma(x + 1, y + 1)
+                            - luma(x - 1, y - 1) - 2.0 * luma(x, y - 1) - luma(x + 1, y - 1);
+
+                        // a hard edge between black and white has a gradient of 4 in one direction
+                        (gx * gx + gy * gy).sqrt() / 4.0
+                    })
+                    .collect();
+
+                // halfway between the darkest and lightest neighbour, which separates the two sides of an edge
+                let midpoints = (0..width * height)
+                    .map(|i| {
+                        let (x, y) = ((i % width) as i64, (i / width) as i64);
+                        let neighbours = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)));
+                        let (min, max) = neighbours.fold((f32::MAX, f32::MIN), |(min, max), (x, y)| {
+                            (min.min(luma(x, y)), max.max(luma(x, y)))
+                        });
+
+                        (min + max) / 2.0
+                    })
+                    .collect();
+
+                Self::Gradient { width, luminance, magnitudes, midpoints, threshold, attenuation }
+            },
+            EdgeDetection::Difference(threshold) => {
+                let colours = image.iter().flatten()
+                    .map(|pixel| space.from_srgb(Srgb::from(*pixel).into_format()))
+                    .collect();
+
+                Self::Difference { width, colours, threshold, attenuation }
+            },
+        }
+    }
+
+    /// The factor that error propagated from one pixel to another is scaled by.
+    ///
+    /// Both pixels must be within the image.
+    pub fn weight(&self, from: (usize, usize), to: (usize, usize)) -> f32 {
+        match self {
+            Self::Gradient { width, luminance, magnitudes, midpoints, threshold, attenuation } => {
+                let (from, to) = (from.1 * width + from.0, to.1 * width + to.0);
+
+                // the edge is wherever the gradient is the strongest
+                let edge = if magnitudes[from] >= magnitudes[to] { from } else { to };
+                let crosses = (luminance[from] > midpoints[edge]) != (luminance[to] > midpoints[edge]);
+
+                match magnitudes[edge] > *threshold && crosses {
+                    true => *attenuation,
+                    false => 1.0,
+                }
+            },
+            Self::Difference { width, colours, threshold, attenuation } => {
+                let (a, b) = (colours[from.1 * width + from.0], colours[to.1 * width + to.0]);
+                let difference = (a.0 - b.0).abs().max((a.1 - b.1).abs()).max((a.2 - b.2).abs());
+
+                match difference > *threshold {
+                    true => *attenuation,
+                    false => 1.0,
+                }
+            },
+        }
+    }
+}
+
+/// Sharpens the image by pushing each pixel away from the average of its four direct neighbours,
+/// scaled by `amount`.
+pub(crate) fn sharpen(image: &RgbImageRepr, amount: f32) -> RgbImageRepr {
+    let (width, height) = get_dimensions_of_matrix(image);
+    let pixel = |x: i64, y: i64| image[y.clamp(0, height as i64 - 1) as usize][x.clamp(0, width as i64 - 1) as usize];
+
+    (0..height as i64)
+        .map(|y| {
+            (0..width as i64)
+                .map(|x| {
+                    let neighbours = [pixel(x - 1, y), pixel(x + 1, y), pixel(x, y - 1), pixel(x, y + 1)];
+                    let centre = pixel(x, y);
+
+                    [0, 1, 2].map(|channel| {
+                        let average = neighbours.iter().map(|pixel| pixel[channel] as f32).sum::<f32>() / 4.0;
+                        let value = centre[channel] as f32;
+                        (value + amount * (value - average)).round().clamp(0.0, 255.0) as u8
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
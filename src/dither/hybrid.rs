@@ -0,0 +1,100 @@
+use palette::Srgb;
+
+use crate::{
+    utils::image::RgbImageRepr,
+    effect::Effect,
+};
+
+use super::{
+    error::{ErrorPropagator, WithPalette, PixelGuide},
+    ordered::{ThresholdMap, Spread},
+    alpha::MaskedDither,
+    indexed::IndexedDither,
+};
+
+/// Represents _threshold modulated_ error propagation, combining a threshold map with an error propagation matrix.
+///
+/// Before picking the closest palette colour, each pixel is offset by the threshold map - the same way as in
+/// ordered dithering. The error is still measured from the pixel without the offset, and propagated as usual.
+/// As described by Eschbach and Knox, this breaks up the "worm" artifacts of error propagation while
+/// also hiding the regular texture of the threshold map.
+///
+/// Any `ErrorPropagator` can be used, such as `FLOYD_STEINBERG.with_palette(palette)`, alongside any
+/// `ThresholdMap`, such as `ThresholdMatrix::bayer(8)` or `ThresholdMatrix::blue_noise(64, 0)`.
+#[derive(Clone)]
+pub struct Hybrid<'a, 'b, M: ThresholdMap> {
+    propagator: ErrorPropagator<'a, 'b, WithPalette>,
+    map: M,
+    amount: f32,
+    spread: Spread,
+}
+
+impl<'a, 'b, M: ThresholdMap> Hybrid<'a, 'b, M> {
+    /// Creates a new `Hybrid` ditherer, with the threshold map at half of its usual strength.
+    ///
+    /// The palette, colour space and every other setting of the propagator are used as-is.
+    pub fn new(propagator: ErrorPropagator<'a, 'b, WithPalette>, map: M) -> Self {
+        Self {
+            propagator,
+            map,
+            amount: 0.5,
+            spread: Spread::Palette,
+        }
+    }
+
+    /// Creates a clone of the ditherer with a different strength of the threshold map, relative to the spread.
+    ///
+    /// `0.0` leaves only the error propagation, while `1.0` offsets each pixel as much as ordered dithering would.
+    /// Defaults to `0.5`.
+    pub fn with_amount(&self, amount: f32) -> Self where M: Clone {
+        Self { amount: amount.max(0.0), propagator: self.propagator.clone(), map: self.map.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different spread, which the amount is relative to.
+    pub fn with_spread(&self, spread: Spread) -> Self where M: Clone {
+        Self { spread, propagator: self.propagator.clone(), map: self.map.clone(), ..*self }
+    }
+
+    fn dither(&self, image: RgbImageRepr, mask: Option<&[Vec<bool>]>) -> (RgbImageRepr, Vec<Vec<usize>>) {
+        let palette = self.propagator.palette_matcher();
+        let spread = self.spread.resolve(&palette, self.propagator.colour_space());
+
+        let modulation = Modulation { map: &self.map, amount: self.amount, spread };
+        self.propagator.propagate(image, mask, &modulation)
+    }
+}
+
+/// Offsets each pixel by the threshold map.
+struct Modulation<'m, M: ThresholdMap> {
+    map: &'m M,
+    amount: f32,
+    spread: [f32; 3],
+}
+
+impl<'m, M: ThresholdMap> PixelGuide for Modulation<'m, M> {
+    fn offset(&self, x: usize, y: usize) -> [f32; 3] {
+        [0, 1, 2].map(|channel| (self.map.channel_threshold(x, y, channel) - 0.5) * self.amount * self.spread[channel])
+    }
+}
+
+impl<'a, 'b, M: ThresholdMap> Effect<RgbImageRepr> for Hybrid<'a, 'b, M> {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        self.dither(image, None).0
+    }
+}
+
+impl<'a, 'b, M: ThresholdMap> MaskedDither for Hybrid<'a, 'b, M> {
+    fn dither_masked(&self, image: RgbImageRepr, mask: &[Vec<bool>]) -> RgbImageRepr {
+        self.dither(image, Some(mask)).0
+    }
+}
+
+impl<'a, 'b, M: ThresholdMap> IndexedDither for Hybrid<'a, 'b, M> {
+    fn palette(&self) -> Vec<Srgb> {
+        self.propagator.palette()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.dither(image, None).1
+    }
+}
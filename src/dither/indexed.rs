@@ -0,0 +1,261 @@
+use std::{collections::HashMap, fmt, fs::File, io::{BufWriter, Write}, path::Path};
+
+use image::{error::{EncodingError, ImageFormatHint}, ImageError, ImageFormat, ImageResult};
+use palette::{Srgb, Srgba, WithAlpha};
+
+use crate::{
+    utils::image::{RgbImageRepr, RgbaImageRepr},
+    colour::utils::BitDepth,
+    effect::Effect,
+};
+
+/// The most colours an indexed image can have, as supported by PNG and GIF.
+pub const MAX_INDEXED_COLOURS: usize = 256;
+
+/// The reasons an indexed image can't be created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexedError {
+    /// There are more colours than an indexed image can have - such as with `BitDepth::RGB565`.
+    TooManyColours(usize),
+
+    /// The palette is empty, so there's nothing for the pixels to refer to.
+    EmptyPalette,
+
+    /// A pixel refers to an index outside of the palette.
+    InvalidIndex(usize),
+}
+
+impl fmt::Display for IndexedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyColours(colours) => {
+                write!(f, "an indexed image can have at most {} colours, but has {}", MAX_INDEXED_COLOURS, colours)
+            },
+            Self::EmptyPalette => write!(f, "the palette of an indexed image can't be empty"),
+            Self::InvalidIndex(index) => write!(f, "the index {} is outside of the palette", index),
+        }
+    }
+}
+
+impl std::error::Error for IndexedError {}
+
+/// An image made of palette indices, alongside the palette itself - as stored by indexed PNGs and GIFs.
+///
+/// Each palette entry has its own alpha, so transparency is supported as well.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedImage {
+    width: usize,
+    height: usize,
+    indices: Vec<u8>,
+    palette: Vec<Srgba>,
+}
+
+impl IndexedImage {
+    /// Creates an indexed image from the palette index of every pixel, row by row.
+    ///
+    /// Rows shorter than the first row are padded with `0`.
+    pub fn from_indices(indices: &[Vec<usize>], palette: &[Srgba]) -> Result<Self, IndexedError> {
+        let (width, height) = (indices.first().map_or(0, Vec::len), indices.len());
+
+        if palette.len() > MAX_INDEXED_COLOURS {
+            return Err(IndexedError::TooManyColours(palette.len()));
+        }
+
+        if palette.is_empty() && width * height > 0 {
+            return Err(IndexedError::EmptyPalette);
+        }
+
+        if let Some(index) = indices.iter().flatten().find(|index| **index >= palette.len()) {
+            return Err(IndexedError::InvalidIndex(*index));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            indices: indices.iter()
+                .flat_map(|row| (0..width).map(|x| row.get(x).copied().unwrap_or(0) as u8))
+                .collect(),
+            palette: palette.to_vec(),
+        })
+    }
+
+    /// Creates an indexed image from an RGB image whose colours come from the palette, such as the output of a ditherer.
+    ///
+    /// Colours are looked up exactly, and the index of each palette colour is kept as-is. Any colours missing
+    /// from the palette get appended to it, so no colour is ever lost - which fails if there are more than `256`
+    /// colours in total.
+    pub fn from_rgb(image: &RgbImageRepr, palette: &[Srgb]) -> Result<Self, IndexedError> {
+        let image: RgbaImageRepr = image.iter()
+            .map(|row| row.iter().map(|[r, g, b]| [*r, *g, *b, 255]).collect())
+            .collect();
+        let palette: Vec<Srgba> = palette.iter().map(|colour| colour.with_alpha(1.0)).collect();
+
+        Self::from_rgba(&image, &palette)
+    }
+
+    /// Creates an indexed image from an RGBA image whose colours come from the palette. See [`IndexedImage::from_rgb`].
+    ///
+    /// Fully transparent pixels all share one entry regardless of their colour - the first fully transparent
+    /// palette entry, or a new one if there isn't any.
+    pub fn from_rgba(image: &RgbaImageRepr, palette: &[Srgba]) -> Result<Self, IndexedError> {
+        let mut palette = palette.to_vec();
+
+        let mut lookup: HashMap<[u8; 4], usize> = HashMap::new();
+        for (index, colour) in palette.iter().enumerate().rev() {
+            lookup.insert(transparent_as_one(colour.into_format().into()), index);
+        }
+
+        let indices: Vec<Vec<usize>> = image.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|pixel| {
+                        *lookup.entry(transparent_as_one(*pixel)).or_insert_with(|| {
+                            palette.push(Srgba::from(*pixel).into_format());
+                            palette.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self::from_indices(&indices, &palette)
+    }
+
+    /// The width and height of the image.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// The palette index of every pixel, row by row.
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// The palette the indices refer to.
+    pub fn palette(&self) -> &[Srgba] {
+        &self.palette
+    }
+
+    /// The palette index of a single pixel.
+    pub fn index(&self, x: usize, y: usize) -> u8 {
+        self.indices[y * self.width + x]
+    }
+
+    /// The fewest bits per pixel able to hold every index - either `1`, `2`, `4` or `8`.
+    pub fn bits_per_pixel(&self) -> u8 {
+        match self.palette.len() {
+            0..=2 => 1,
+            3..=4 => 2,
+            5..=16 => 4,
+            _ => 8,
+        }
+    }
+
+    /// Converts the image back into RGBA pixels.
+    pub fn to_rgba(&self) -> RgbaImageRepr {
+        let palette: Vec<[u8; 4]> = self.palette.iter().map(|colour| colour.into_format().into()).collect();
+
+        self.indices.chunks(self.width.max(1))
+            .map(|row| row.iter().map(|index| palette[*index as usize]).collect())
+            .collect()
+    }
+
+    /// Encodes the image as an indexed PNG, using the fewest bits per pixel possible.
+    ///
+    /// The palette is stored in a `PLTE` chunk, and the alpha of its entries in a `tRNS` chunk - which
+    /// is left out entirely if the palette is fully opaque.
+    pub fn write_png<W: Write>(&self, writer: W) -> ImageResult<()> {
+        let bits = self.bits_per_pixel();
+        let palette: Vec<[u8; 4]> = match self.palette.is_empty() {
+            true => vec![[0, 0, 0, 255]],
+            false => self.palette.iter().map(|colour| colour.into_format().into()).collect(),
+        };
+
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(match bits {
+            1 => png::BitDepth::One,
+            2 => png::BitDepth::Two,
+            4 => png::BitDepth::Four,
+            _ => png::BitDepth::Eight,
+        });
+        encoder.set_palette(palette.iter().flat_map(|[r, g, b, _]| [*r, *g, *b]).collect::<Vec<_>>());
+
+        // trailing opaque entries can be left out of the tRNS chunk
+        if let Some(last) = palette.iter().rposition(|[_, _, _, a]| *a < 255) {
+            encoder.set_trns(palette[..=last].iter().map(|[_, _, _, a]| *a).collect::<Vec<_>>());
+        }
+
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        writer.write_image_data(&self.packed_rows(bits)).map_err(png_error)?;
+        writer.finish().map_err(png_error)
+    }
+
+    /// Saves the image as an indexed PNG. See [`IndexedImage::write_png`].
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    /// Packs the indices of each row into bytes, leftmost pixel in the highest bits - with every row
+    /// starting on a new byte.
+    fn packed_rows(&self, bits: u8) -> Vec<u8> {
+        let per_byte = (8 / bits) as usize;
+
+        self.indices.chunks(self.width.max(1))
+            .flat_map(|row| {
+                row.chunks(per_byte).map(|pixels| {
+                    pixels.iter().enumerate().fold(0, |byte, (i, index)| {
+                        byte | index << (8 - bits as usize * (i + 1))
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// Makes every fully transparent colour look the same, so that they share a palette entry.
+fn transparent_as_one(pixel: [u8; 4]) -> [u8; 4] {
+    match pixel[3] {
+        0 => [0; 4],
+        _ => pixel,
+    }
+}
+
+fn png_error(error: png::EncodingError) -> ImageError {
+    match error {
+        png::EncodingError::IoError(error) => ImageError::IoError(error),
+        error => ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(ImageFormat::Png), error)),
+    }
+}
+
+/// Defines a ditherer that can output an indexed image, rather than RGB pixels.
+pub trait IndexedDither: Effect<RgbImageRepr> {
+    /// The palette the ditherer picks its colours from, in order.
+    fn palette(&self) -> Vec<Srgb>;
+
+    /// Dithers the image, returning the index of the palette colour picked for each pixel, row by row.
+    ///
+    /// These are the same colours as the output of `affect`, without looking them up in the palette again.
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>>;
+
+    /// Dithers the image, returning the index of the palette colour picked for each pixel alongside the palette.
+    ///
+    /// Fails if the palette is empty or has more than `256` colours - such as with `BitDepth::RGB565`.
+    fn dither_indexed(&self, image: RgbImageRepr) -> Result<IndexedImage, IndexedError> {
+        let palette: Vec<Srgba> = self.palette().iter().map(|colour| colour.with_alpha(1.0)).collect();
+
+        if palette.len() > MAX_INDEXED_COLOURS {
+            return Err(IndexedError::TooManyColours(palette.len()));
+        }
+
+        IndexedImage::from_indices(&self.dither_indices(image), &palette)
+    }
+}
+
+/// The palette of a ditherer that can use either a palette or a bit depth.
+pub(crate) fn indexed_palette(palette: &[Srgb], bit_depth: Option<BitDepth>) -> Vec<Srgb> {
+    match bit_depth {
+        Some(bit_depth) => bit_depth.palette(),
+        None => palette.to_vec(),
+    }
+}
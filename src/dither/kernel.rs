@@ -0,0 +1,331 @@
+use std::{fmt, str::FromStr};
+
+use serde_json::Value;
+
+use super::error::{ErrorPropagator, PropagatorState, Base};
+
+/// An error propagation matrix that is owned rather than borrowed, so that it can be defined at runtime -
+/// for example parsed from text or JSON.
+///
+/// The text notation is the same grid used in the documentation of the built-in propagators, with rows
+/// separated by either new lines or `/`:
+///
+/// ```notrust
+/// - x 7
+/// 5 3 1
+/// ```
+///
+/// Here, `x` marks the current pixel, `-` marks positions that receive no error, and every number is the
+/// portion of error the position receives. Each row must have the same width, and error can only be
+/// propagated to pixels after the current one.
+///
+/// A kernel can't be used as an effect directly - instead, `.propagator` returns an `ErrorPropagator` borrowing from it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Kernel {
+    name: String,
+    matrix: Vec<(i8, i8, u8)>,
+    portions: u16,
+}
+
+impl Kernel {
+    /// Creates a new kernel from a matrix in the form of (dx, dy, portion), like `ErrorPropagator::matrix`.
+    pub fn new(name: impl Into<String>, matrix: Vec<(i8, i8, u8)>, portions: u16) -> Self {
+        Self { name: name.into(), matrix, portions }
+    }
+
+    /// Parses a kernel from its text notation, such as `- x 7 / 5 3 1`.
+    ///
+    /// The total amount of portions is the sum of every portion, so the kernel distributes the entire error.
+    /// Use `.with_portions` for kernels that don't, such as Atkinson.
+    pub fn parse(text: &str) -> Result<Self, KernelError> {
+        let rows: Vec<Vec<String>> = text
+            .split(['\n', '/'])
+            .map(|row| row.split_whitespace().map(String::from).collect::<Vec<_>>())
+            .filter(|row| !row.is_empty())
+            .collect();
+
+        Self::from_rows(&rows)
+    }
+
+    /// Parses a kernel from a JSON object.
+    ///
+    /// The grid is either given as `"kernel"`, in the text notation, or as `"rows"` - an array of rows where
+    /// each entry is a portion, `"x"` or `"-"`. The `"name"` and the total amount of `"portions"` are optional.
+    ///
+    /// ```notrust
+    /// { "name": "floyd-steinberg", "rows": [["-", "x", 7], [5, 3, 1]], "portions": 16 }
+    /// { "name": "atkinson", "kernel": "- x 1 1 / 1 1 1 - / - 1 - -", "portions": 8 }
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, KernelError> {
+        let value: Value = serde_json::from_str(json).map_err(|error| KernelError::Json(error.to_string()))?;
+        let invalid = |message: &str| KernelError::Json(message.to_string());
+
+        let object = value.as_object().ok_or_else(|| invalid("expected an object"))?;
+
+        let kernel = match (object.get("kernel"), object.get("rows")) {
+            (Some(Value::String(text)), None) => Self::parse(text)?,
+            (None, Some(Value::Array(rows))) => {
+                let rows = rows.iter()
+                    .map(|row| {
+                        let row = row.as_array().ok_or_else(|| invalid("expected each row to be an array"))?;
+                        row.iter()
+                            .map(|entry| match entry {
+                                Value::String(entry) => Ok(entry.clone()),
+                                Value::Number(entry) => Ok(entry.to_string()),
+                                _ => Err(invalid("expected each entry to be a portion, \"x\" or \"-\"")),
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Self::from_rows(&rows)?
+            },
+            _ => return Err(invalid("expected either a \"kernel\" string or a \"rows\" array")),
+        };
+
+        let kernel = match object.get("name") {
+            Some(Value::String(name)) => kernel.with_name(name),
+            Some(_) => return Err(invalid("expected \"name\" to be a string")),
+            None => kernel,
+        };
+
+        match object.get("portions") {
+            Some(portions) => {
+                let portions = portions.as_u64()
+                    .and_then(|portions| u16::try_from(portions).ok())
+                    .ok_or_else(|| invalid("expected \"portions\" to be an integer between 0 and 65535"))?;
+                kernel.checked_with_portions(portions)
+            },
+            None => Ok(kernel),
+        }
+    }
+
+    fn from_rows(rows: &[Vec<String>]) -> Result<Self, KernelError> {
+        let width = rows.first().map(|row| row.len()).unwrap_or(0);
+
+        let mut marker = None;
+        let mut entries = Vec::new();
+
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(KernelError::UnevenRows { row: y });
+            }
+
+            for (x, entry) in row.iter().enumerate() {
+                match entry.as_str() {
+                    "x" | "X" if marker.is_some() => return Err(KernelError::MultipleMarkers),
+                    "x" | "X" => marker = Some((x, y)),
+                    "-" | "." => {},
+                    entry => match entry.parse::<u8>() {
+                        Ok(0) => {},
+                        Ok(portion) => entries.push((x, y, portion)),
+                        Err(_) => return Err(KernelError::InvalidEntry { row: y, column: x, entry: entry.to_string() }),
+                    },
+                }
+            }
+        }
+
+        let (mx, my) = marker.ok_or(KernelError::MissingMarker)?;
+
+        let matrix = entries.into_iter()
+            .map(|(x, y, portion)| {
+                if y < my || (y == my && x <= mx) {
+                    return Err(KernelError::PropagatesBackwards { row: y, column: x });
+                }
+
+                let dx = i8::try_from(x as i64 - mx as i64).map_err(|_| KernelError::TooLarge)?;
+                let dy = i8::try_from(y - my).map_err(|_| KernelError::TooLarge)?;
+                Ok((dx, dy, portion))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // an empty kernel still needs portions, even though it has nothing to distribute
+        let portions = matrix.iter().map(|(_, _, portion)| *portion as u32).sum::<u32>().max(1);
+        let portions = u16::try_from(portions).map_err(|_| KernelError::TooLarge)?;
+
+        Ok(Self::new("custom", matrix, portions))
+    }
+
+    /// Creates a clone of the kernel with a different name.
+    pub fn with_name(&self, name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..self.clone() }
+    }
+
+    /// Creates a clone of the kernel with a different total amount of portions.
+    ///
+    /// This decides how much of the error gets distributed - see `ErrorPropagator::portions`.
+    pub fn with_portions(&self, portions: u16) -> Self {
+        Self { portions, ..self.clone() }
+    }
+
+    fn checked_with_portions(&self, portions: u16) -> Result<Self, KernelError> {
+        match portions {
+            0 => Err(KernelError::NoPortions),
+            portions => Ok(self.with_portions(portions)),
+        }
+    }
+
+    /// The name of the kernel, which is `"custom"` unless given.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The error propagation matrix, in the form of (dx, dy, portion).
+    pub fn matrix(&self) -> &[(i8, i8, u8)] {
+        &self.matrix
+    }
+
+    /// The total amount of portions.
+    pub fn portions(&self) -> u16 {
+        self.portions
+    }
+
+    /// The fraction of the error that gets distributed - `1.0` being all of it.
+    pub fn distributed(&self) -> f32 {
+        let distributed: u32 = self.matrix.iter().map(|(_, _, portion)| *portion as u32).sum();
+
+        match distributed {
+            0 => 0.0,
+            distributed => distributed as f32 / self.portions as f32,
+        }
+    }
+
+    /// Lists anything unusual about the kernel that still results in a working propagator.
+    pub fn warnings(&self) -> Vec<KernelWarning> {
+        let distributed = self.distributed();
+        let mut warnings = Vec::new();
+
+        if self.matrix.is_empty() {
+            warnings.push(KernelWarning::Empty);
+        } else if distributed < 1.0 {
+            warnings.push(KernelWarning::UnderPropagates(distributed));
+        } else if distributed > 1.0 {
+            warnings.push(KernelWarning::OverPropagates(distributed));
+        }
+
+        warnings
+    }
+
+    /// Creates an `ErrorPropagator` using the kernel, which can be configured like any of the constants.
+    pub fn propagator(&self) -> ErrorPropagator<'_, '_, Base> {
+        ErrorPropagator::new(&self.name, &self.matrix, self.portions)
+    }
+}
+
+impl<'a, 'b, S: PropagatorState> From<&ErrorPropagator<'a, 'b, S>> for Kernel {
+    fn from(propagator: &ErrorPropagator<'a, 'b, S>) -> Self {
+        Self::new(propagator.name, propagator.matrix.to_vec(), propagator.portions)
+    }
+}
+
+impl FromStr for Kernel {
+    type Err = KernelError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+/// Writes the kernel in its text notation, one row per line.
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offsets = self.matrix.iter().map(|(dx, dy, _)| (*dx as i64, *dy as i64));
+        let left = offsets.clone().map(|(dx, _)| dx).min().unwrap_or(0).min(0);
+        let right = offsets.clone().map(|(dx, _)| dx).max().unwrap_or(0).max(0);
+        let depth = offsets.map(|(_, dy)| dy).max().unwrap_or(0).max(0);
+
+        for dy in 0..=depth {
+            let row = (left..=right)
+                .map(|dx| match (dx, dy) {
+                    (0, 0) => "x".to_string(),
+                    _ => self.matrix.iter()
+                        .find(|(x, y, _)| (*x as i64, *y as i64) == (dx, dy))
+                        .map(|(_, _, portion)| portion.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            match dy {
+                0 => write!(f, "{}", row)?,
+                _ => write!(f, "\n{}", row)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The reasons a kernel can fail to be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KernelError {
+    /// There is no `x` marking the current pixel.
+    MissingMarker,
+
+    /// There is more than one `x`.
+    MultipleMarkers,
+
+    /// An entry is neither a portion between `0` and `255`, `x` nor `-`.
+    InvalidEntry { row: usize, column: usize, entry: String },
+
+    /// A row has a different width than the first one.
+    UnevenRows { row: usize },
+
+    /// A portion is placed on a pixel that has already been dithered by the time the current one is.
+    PropagatesBackwards { row: usize, column: usize },
+
+    /// The total amount of portions is given as `0`.
+    NoPortions,
+
+    /// The kernel is too large - reaching further than `127` pixels, or with more than `65535` portions.
+    TooLarge,
+
+    /// The JSON is invalid, or doesn't describe a kernel.
+    Json(String),
+}
+
+impl fmt::Display for KernelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingMarker => write!(f, "the kernel has no `x` marking the current pixel"),
+            Self::MultipleMarkers => write!(f, "the kernel has more than one `x`"),
+            Self::InvalidEntry { row, column, entry } => {
+                write!(f, "invalid entry `{}` at row {}, column {} - expected a portion, `x` or `-`", entry, row + 1, column + 1)
+            },
+            Self::UnevenRows { row } => write!(f, "row {} has a different width than the first row", row + 1),
+            Self::PropagatesBackwards { row, column } => {
+                write!(f, "the portion at row {}, column {} is before the current pixel", row + 1, column + 1)
+            },
+            Self::NoPortions => write!(f, "the kernel has no portions"),
+            Self::TooLarge => write!(f, "the kernel is too large"),
+            Self::Json(message) => write!(f, "invalid kernel JSON: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for KernelError {}
+
+/// Something unusual about a kernel, that still results in a working propagator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KernelWarning {
+    /// Only the given fraction of the error is distributed, which loses detail in highlights and shadows -
+    /// like Atkinson does on purpose.
+    UnderPropagates(f32),
+
+    /// More than all of the error is distributed, given as a fraction - which can amplify the error until
+    /// it saturates.
+    OverPropagates(f32),
+
+    /// No error is distributed at all, which is the same as picking the closest colour for each pixel.
+    Empty,
+}
+
+impl fmt::Display for KernelWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnderPropagates(fraction) => write!(f, "only {:.1}% of the error is distributed", fraction * 100.0),
+            Self::OverPropagates(fraction) => write!(f, "{:.1}% of the error is distributed", fraction * 100.0),
+            Self::Empty => write!(f, "no error is distributed"),
+        }
+    }
+}
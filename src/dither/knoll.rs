@@ -0,0 +1,133 @@
+use palette::Srgb;
+
+use crate::{
+    utils::image::RgbImageRepr,
+    colour::{utils::PaletteMatcher, comparisons::ColourDistance, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::{ordered::{ThresholdMap, dither_planned, palette_luma}, indexed::IndexedDither};
+
+/// Represents Thomas Knoll's _pattern dithering_, the ordered dithering algorithm used by Adobe Photoshop.
+///
+/// For each colour it builds a list of candidates by repeatedly quantizing the colour with the error of the
+/// previous candidates added to it, so that the average of the candidates approaches the colour. The candidates
+/// are then sorted by luminance, and the threshold map picks which one gets drawn.
+///
+/// Much like `Yliluoma`, this works far better than `Bayer` for palettes with many colours - while being cheaper,
+/// since each candidate only takes a single lookup of the closest colour.
+#[derive(Clone)]
+pub struct Knoll<M: ThresholdMap> {
+    map: M,
+    palette: Vec<Srgb>,
+    candidates: usize,
+    error_multiplier: f32,
+    distance: ColourDistance,
+    colour_space: ColourSpace,
+}
+
+impl<M: ThresholdMap> Knoll<M> {
+    /// Creates a new `Knoll` ditherer with the given threshold map, such as `ThresholdMatrix::bayer(8)`.
+    ///
+    /// The amount of candidates defaults to the amount of thresholds in the map, up to `64` - which
+    /// is also used for maps that never repeat.
+    pub fn new(map: M, palette: Vec<Srgb>) -> Self {
+        let thresholds = map.dimensions().map_or(64, |(xdim, ydim)| xdim * ydim);
+
+        Self {
+            map,
+            palette,
+            candidates: thresholds.clamp(2, 64),
+            error_multiplier: 1.0,
+            distance: ColourDistance::WeightedRgb,
+            colour_space: ColourSpace::Srgb,
+        }
+    }
+
+    /// Creates a clone of the ditherer that builds a different amount of candidates for each colour.
+    ///
+    /// More candidates approximate the colour more closely, but there's no use in having more of them
+    /// than there are thresholds in the map.
+    pub fn with_candidates(&self, candidates: usize) -> Self where M: Clone {
+        Self { candidates: candidates.max(1), map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different multiplier for the error added before each quantization.
+    ///
+    /// Lower values keep the candidates closer to the colour itself, resulting in less noise but more banding.
+    pub fn with_error_multiplier(&self, error_multiplier: f32) -> Self where M: Clone {
+        Self { error_multiplier, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different colour distance metric.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self where M: Clone {
+        Self { distance, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that accumulates error in a different colour space.
+    pub fn with_colour_space(&self, colour_space: ColourSpace) -> Self where M: Clone {
+        Self { colour_space, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Builds the sorted list of candidates for a colour.
+    fn plan(&self, colour: Srgb, palette: &PaletteMatcher, palette_in_space: &[(f32, f32, f32)], luma: &[f32]) -> Vec<usize> {
+        let space = self.colour_space;
+        let original = space.from_srgb(colour);
+
+        let mut error = (0.0, 0.0, 0.0);
+        let mut plan = Vec::with_capacity(self.candidates);
+
+        for _ in 0..self.candidates {
+            let attempt = (
+                original.0 + error.0 * self.error_multiplier,
+                original.1 + error.1 * self.error_multiplier,
+                original.2 + error.2 * self.error_multiplier,
+            );
+
+            let index = palette.nearest_index_in(attempt, space);
+            let candidate = palette_in_space[index];
+
+            error = (
+                error.0 + original.0 - candidate.0,
+                error.1 + original.1 - candidate.1,
+                error.2 + original.2 - candidate.2,
+            );
+
+            plan.push(index);
+        }
+
+        plan.sort_by(|a, b| luma[*a].partial_cmp(&luma[*b]).unwrap_or(std::cmp::Ordering::Equal));
+        plan
+    }
+}
+
+impl<M: ThresholdMap> Knoll<M> {
+    fn dither(&self, image: RgbImageRepr) -> (RgbImageRepr, Vec<Vec<usize>>) {
+        if self.palette.is_empty() {
+            let indices = vec![vec![0; image.first().map_or(0, Vec::len)]; image.len()];
+            return (image, indices);
+        }
+
+        let palette = PaletteMatcher::new(&self.palette, self.distance);
+        let palette_in_space: Vec<_> = self.palette.iter().map(|&colour| self.colour_space.from_srgb(colour)).collect();
+        let luma = palette_luma(&self.palette);
+
+        dither_planned(image, &self.map, &self.palette, |colour| self.plan(colour, &palette, &palette_in_space, &luma))
+    }
+}
+
+impl<M: ThresholdMap> Effect<RgbImageRepr> for Knoll<M> {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        self.dither(image).0
+    }
+}
+
+impl<M: ThresholdMap> IndexedDither for Knoll<M> {
+    fn palette(&self) -> Vec<Srgb> {
+        self.palette.clone()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.dither(image).1
+    }
+}
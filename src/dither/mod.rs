@@ -1,9 +1,54 @@
 /// Contains all the dithering logic for the error propagation based algorithms.
 pub mod error;
 
-/// Contains logic for Ordered / Bayer dithering.
+/// Contains owned error propagation kernels, which can be defined at runtime and parsed from text or JSON.
+pub mod kernel;
+
+/// Contains edge detection for edge-aware error propagation.
+pub mod edges;
+
+/// Contains logic for Knuth's dot diffusion.
+pub mod dot_diffusion;
+
+/// Contains the Bayer matrix for ordered dithering, along with the original `Bayer` ditherer.
 pub mod bayer;
 
+/// Contains the threshold maps and generic logic for ordered dithering.
+pub mod ordered;
+
+/// Contains logic for Yliluoma's arbitrary-palette ordered dithering.
+pub mod yliluoma;
+
+/// Contains logic for Knoll's pattern dithering.
+pub mod knoll;
+
+/// Contains logic for ordered dithering using blue noise, generated with the void-and-cluster algorithm.
+pub mod blue_noise;
+
+/// Contains logic for ordered dithering using seeded white noise and interleaved gradient noise.
+pub mod noise;
+
+/// Contains logic for direct binary search halftoning, which refines the output of another ditherer.
+pub mod direct_binary_search;
+
+/// Contains logic for Riemersma dithering, which follows a generalized Hilbert curve.
+pub mod riemersma;
+
+/// Contains logic for hybrid dithering, where a threshold map modulates error propagation.
+pub mod hybrid;
+
+/// Contains logic for dithering with attribute clash, following the colour constraints of retro hardware.
+pub mod attribute;
+
+/// Contains logic for dithering images with transparency.
+pub mod alpha;
+
+/// Contains logic for dithering animations without flickering.
+pub mod animated;
+
+/// Contains indexed images made of palette indices, and their encoding as indexed PNGs.
+pub mod indexed;
+
 pub use error::{
     FLOYD_STEINBERG,
     JARVIS_JUDICE_NINKE,
@@ -13,4 +58,6 @@ pub use error::{
     SIERRA,
     SIERRA_TWO_ROW,
     SIERRA_LITE,
+    OSTROMOUKHOV,
+    ScanOrder,
 };
\ No newline at end of file
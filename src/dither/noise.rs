@@ -0,0 +1,99 @@
+use palette::Srgb;
+
+use crate::utils::random::SplitMix64;
+
+use super::ordered::{Ordered, ThresholdMap};
+
+/// The distribution of the thresholds in white noise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoiseDistribution {
+    /// Every threshold is equally likely. This is the default.
+    #[default]
+    Uniform,
+
+    /// The average of two uniform distributions, as used for _TPDF_ dither in audio - thresholds near the middle
+    /// are more likely than those near the edges.
+    ///
+    /// Audio dither spans two levels instead of one, which makes the amount of noise independent of the colour
+    /// being dithered at the cost of more noise overall. Doubling the spread with `Ordered::with_spread` does the same.
+    Triangular,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Noise {
+    White(NoiseDistribution),
+    /// Shifted by the `(x, y)` offset.
+    InterleavedGradient(usize, usize),
+}
+
+/// A threshold map generated from seeded noise, one pixel at a time.
+///
+/// The same seed always results in the same map. It's computed from the raw coordinates, so it never repeats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoiseMap {
+    noise: Noise,
+    seed: u64,
+}
+
+impl NoiseMap {
+    /// The range of the offsets that seeds shift interleaved gradient noise by.
+    const MAX_OFFSET: usize = 256;
+
+    /// White noise, where each threshold is random and independent of its neighbours.
+    pub fn white(seed: u64, distribution: NoiseDistribution) -> Self {
+        Self { noise: Noise::White(distribution), seed }
+    }
+
+    /// Jorge Jimenez's _interleaved gradient noise_, which sits between white noise and a Bayer matrix -
+    /// it has less low-frequency noise than white noise, without a visible cross-hatch pattern.
+    ///
+    /// The seed shifts the noise, so that different seeds result in different maps.
+    pub fn interleaved_gradient(seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let (x, y) = (rng.below(Self::MAX_OFFSET), rng.below(Self::MAX_OFFSET));
+
+        Self { noise: Noise::InterleavedGradient(x, y), seed }
+    }
+}
+
+impl ThresholdMap for NoiseMap {
+    fn threshold(&self, x: usize, y: usize) -> f32 {
+        match self.noise {
+            Noise::White(NoiseDistribution::Uniform) => SplitMix64::at(self.seed, x, y).next_f32(),
+            Noise::White(NoiseDistribution::Triangular) => {
+                let mut rng = SplitMix64::at(self.seed, x, y);
+                (rng.next_f32() + rng.next_f32()) / 2.0
+            },
+            Noise::InterleavedGradient(dx, dy) => {
+                let (x, y) = ((x + dx) as f64, (y + dy) as f64);
+
+                (52.982_918_9 * (0.067_110_56 * x + 0.005_837_15 * y).fract()).fract() as f32
+            },
+        }
+    }
+
+    fn dimensions(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+impl Ordered<NoiseMap> {
+    /// Creates a new `Ordered` ditherer using seeded, uniformly distributed white noise as the threshold map.
+    ///
+    /// The noise makes it the most basic form of dithering - it doesn't leave any patterns, but it's also the
+    /// noisiest. Mostly useful as a baseline, or for reducing bit depth where patterns are unwanted.
+    ///
+    /// The same `seed` always results in the same output. For other distributions, use `Ordered::with_map`
+    /// with `NoiseMap::white`.
+    pub fn white_noise(seed: u64, palette: Vec<Srgb>) -> Self {
+        Self::new(NoiseMap::white(seed, NoiseDistribution::Uniform), palette)
+    }
+
+    /// Creates a new `Ordered` ditherer using interleaved gradient noise as the threshold map.
+    ///
+    /// Cheap to compute for every pixel on its own, while looking less noisy than white noise.
+    /// The same `seed` always results in the same output.
+    pub fn interleaved_gradient_noise(seed: u64, palette: Vec<Srgb>) -> Self {
+        Self::new(NoiseMap::interleaved_gradient(seed), palette)
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+
+use image::DynamicImage;
+use ndarray::Array2;
+use palette::Srgb;
+
+use crate::{
+    utils::image::RgbImageRepr,
+    colour::{utils::{BitDepth, PaletteMatcher}, comparisons::ColourDistance, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::{bayer::dither_matrix, blue_noise::void_and_cluster, indexed::{indexed_palette, IndexedDither}};
+
+/// Defines a map of thresholds for ordered dithering, which gets tiled across the image.
+///
+/// Each threshold decides how much a pixel is pushed towards a lighter or darker palette colour - `0.0` being
+/// the darkest and `1.0` being the lightest.
+pub trait ThresholdMap {
+    /// The threshold at the given coordinates, in the range `0.0..1.0`.
+    ///
+    /// Coordinates outside of the map should wrap around, so that it tiles.
+    fn threshold(&self, x: usize, y: usize) -> f32;
+
+    /// The threshold for a specific channel - `0`, `1` and `2` for red, green and blue respectively.
+    ///
+    /// Defaults to using the same threshold for every channel.
+    fn channel_threshold(&self, x: usize, y: usize, _channel: usize) -> f32 {
+        self.threshold(x, y)
+    }
+
+    /// The dimensions of the map before it starts repeating, as `(width, height)` - or `None` if it never
+    /// repeats, such as with noise.
+    fn dimensions(&self) -> Option<(usize, usize)>;
+}
+
+/// How far the threshold map pushes each pixel before the closest palette colour is picked.
+///
+/// A threshold of `0.0` offsets each channel by `-spread / 2`, while a threshold of `1.0` offsets it by `spread / 2`.
+///
+/// In colour spaces that separate lightness from colour - such as `ColourSpace::Lab` and `ColourSpace::Oklab` -
+/// only the lightness is offset, so that neutral colours don't pick up colour noise.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Spread {
+    /// Derives the spread from the palette, as the average distance between each colour and its closest
+    /// neighbour - using the largest per-channel difference in the configured colour space, or only the difference
+    /// in lightness in colour spaces that separate it from colour. This is the default.
+    ///
+    /// Sparse palettes get a wide spread so that they still dither, while dense palettes get a narrow spread
+    /// to avoid excessive noise. When quantizing to a bit depth, each channel is spread by the distance between
+    /// two of its levels instead.
+    #[default]
+    Palette,
+
+    /// A fixed spread, in the units of the configured colour space - `1.0` spans an entire sRGB channel.
+    Fixed(f32),
+}
+
+impl Spread {
+    /// Resolves the spread of each channel for the given palette, in `space`.
+    pub fn resolve(&self, palette: &PaletteMatcher, space: ColourSpace) -> [f32; 3] {
+        let lightness_only = !matches!(space, ColourSpace::Srgb | ColourSpace::LinearRgb);
+
+        let spread = match (self, palette.bit_depth()) {
+            (Spread::Fixed(spread), _) => [*spread; 3],
+            (Spread::Palette, Some(bit_depth)) => bit_depth.steps(),
+            (Spread::Palette, None) => {
+                let colours: Vec<_> = palette.palette().iter().map(|&colour| space.from_srgb(colour)).collect();
+
+                let distance = |a: &(f32, f32, f32), b: &(f32, f32, f32)| match lightness_only {
+                    true => (a.0 - b.0).abs(),
+                    false => (a.0 - b.0).abs().max((a.1 - b.1).abs()).max((a.2 - b.2).abs()),
+                };
+
+                // the colour itself and colours that only differ in hue aren't neighbours
+                let nearest = colours.iter().filter_map(|a| {
+                    colours.iter()
+                        .map(|b| distance(a, b))
+                        .filter(|distance| *distance > 0.0)
+                        .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                });
+
+                let (total, count) = nearest.fold((0.0, 0), |(total, count), distance| (total + distance, count + 1));
+
+                [if count == 0 { 0.0 } else { total / count as f32 }; 3]
+            },
+        };
+
+        match lightness_only {
+            true => [spread[0], 0.0, 0.0],
+            false => spread,
+        }
+    }
+}
+
+/// A threshold map backed by a matrix, which covers every built-in map as well as user-provided ones.
+#[derive(Clone)]
+pub struct ThresholdMatrix {
+    matrix: Array2<f32>,
+}
+
+impl ThresholdMatrix {
+    /// Creates a threshold map from a matrix of _ranks_, with each row being a list of ranks.
+    ///
+    /// The ranks decide the order in which pixels turn on, and are scaled by the highest rank - so
+    /// `[[0, 2], [3, 1]]` results in the thresholds `[[0.125, 0.625], [0.875, 0.375]]`.
+    /// Rows shorter than the first row are padded with `0`.
+    pub fn new(ranks: Vec<Vec<u32>>) -> Self {
+        let ydim = ranks.len();
+        let xdim = ranks.first().map(|row| row.len()).unwrap_or(0);
+        let levels = ranks.iter().flatten().max().map_or(1, |max| max + 1) as f32;
+
+        Self {
+            matrix: Array2::from_shape_fn((ydim, xdim), |(y, x)| {
+                (ranks[y].get(x).copied().unwrap_or(0) as f32 + 0.5) / levels
+            }),
+        }
+    }
+
+    /// Creates a threshold map from a matrix of thresholds, each of which should be in the range `0.0..1.0`.
+    pub fn from_thresholds(matrix: Array2<f32>) -> Self {
+        Self { matrix }
+    }
+
+    /// Creates a threshold map from a grayscale image, using the luminance of each pixel as its threshold.
+    ///
+    /// This is useful for loading threshold textures, such as pre-generated blue noise.
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let image = image.to_luma8();
+        let (xdim, ydim) = image.dimensions();
+
+        Self {
+            matrix: Array2::from_shape_fn((ydim as usize, xdim as usize), |(y, x)| {
+                (image.get_pixel(x as u32, y as u32).0[0] as f32 + 0.5) / 256.0
+            }),
+        }
+    }
+
+    /// The recursive Bayer matrix with the given size, rounded up to a power of two.
+    ///
+    /// The thresholds are centred the same way as in `ThresholdMatrix::new`, so this is the same as passing the
+    /// ranks of the Bayer matrix to it.
+    pub fn bayer(size: usize) -> Self {
+        let size = size.max(1).next_power_of_two();
+        let levels = (size * size) as f32;
+
+        Self {
+            matrix: dither_matrix(size).mapv(|threshold| threshold as f32 + 0.5 / levels).reversed_axes(),
+        }
+    }
+
+    /// A blue-noise matrix with the given size, generated with the void-and-cluster algorithm.
+    pub fn blue_noise(size: usize, seed: u64) -> Self {
+        Self { matrix: void_and_cluster(size.max(1), seed) }
+    }
+
+    /// A _clustered-dot_ matrix, where pixels turn on in a spiral starting from the centre of the tile.
+    ///
+    /// This imitates the round dots of a traditional halftone screen.
+    pub fn clustered_dot(size: usize) -> Self {
+        let centre = (size as f32 - 1.0) / 2.0;
+
+        Self::from_order(size, size, |x, y| {
+            let (dx, dy) = (x as f32 - centre, y as f32 - centre);
+            (dx * dx + dy * dy, dy.atan2(dx))
+        })
+    }
+
+    /// A line screen with horizontal lines, which thicken from the centre of the tile outwards.
+    pub fn horizontal_lines(size: usize) -> Self {
+        let centre = (size as f32 - 1.0) / 2.0;
+        Self::from_order(1, size, |_, y| ((y as f32 - centre).abs(), y as f32))
+    }
+
+    /// A line screen with vertical lines, which thicken from the centre of the tile outwards.
+    pub fn vertical_lines(size: usize) -> Self {
+        let centre = (size as f32 - 1.0) / 2.0;
+        Self::from_order(size, 1, |x, _| ((x as f32 - centre).abs(), x as f32))
+    }
+
+    /// A line screen with diagonal lines, which thicken from the centre of the tile outwards.
+    pub fn diagonal(size: usize) -> Self {
+        let centre = (size as f32 - 1.0) / 2.0;
+        Self::from_order(size, size, |x, y| {
+            let line = ((x + y) % size) as f32;
+            ((line - centre).abs(), line)
+        })
+    }
+
+    /// A dispersed-dot `3x3` matrix.
+    pub fn three_by_three() -> Self {
+        Self::new(vec![
+            vec![0, 7, 3],
+            vec![6, 5, 2],
+            vec![4, 1, 8],
+        ])
+    }
+
+    /// A dispersed-dot `5x5` matrix.
+    pub fn five_by_five() -> Self {
+        Self::new(vec![
+            vec![ 0, 12,  3,  5, 20],
+            vec![16, 23, 14, 18,  8],
+            vec![ 4,  9,  1, 21, 11],
+            vec![22, 17,  6, 15,  2],
+            vec![ 7, 19, 10, 24, 13],
+        ])
+    }
+
+    /// Ranks every position of a `xdim * ydim` matrix by the key, lowest first.
+    fn from_order(xdim: usize, ydim: usize, key: impl Fn(usize, usize) -> (f32, f32)) -> Self {
+        let (xdim, ydim) = (xdim.max(1), ydim.max(1));
+
+        let mut positions: Vec<(usize, usize)> = (0..ydim)
+            .flat_map(|y| (0..xdim).map(move |x| (x, y)))
+            .collect();
+        positions.sort_by(|a, b| key(a.0, a.1).partial_cmp(&key(b.0, b.1)).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut ranks = vec![vec![0; xdim]; ydim];
+        for (rank, (x, y)) in positions.into_iter().enumerate() {
+            ranks[y][x] = rank as u32;
+        }
+
+        Self::new(ranks)
+    }
+}
+
+impl ThresholdMap for ThresholdMatrix {
+    fn threshold(&self, x: usize, y: usize) -> f32 {
+        let (ydim, xdim) = self.matrix.dim();
+        if xdim == 0 || ydim == 0 {
+            return 0.5;
+        }
+        self.matrix[(y % ydim, x % xdim)]
+    }
+
+    fn dimensions(&self) -> Option<(usize, usize)> {
+        let (ydim, xdim) = self.matrix.dim();
+        Some((xdim, ydim))
+    }
+}
+
+/// Wraps a threshold map so that each channel uses a shifted and/or rotated version of it.
+///
+/// Since the channels no longer line up, the pattern of the map becomes less visible on colour output.
+#[derive(Clone)]
+pub struct PerChannel<M: ThresholdMap> {
+    map: M,
+    offsets: [(usize, usize); 3],
+    rotations: [u8; 3],
+}
+
+impl<M: ThresholdMap> PerChannel<M> {
+    /// Shifts the map by a different `(x, y)` offset for each channel.
+    pub fn offset(map: M, offsets: [(usize, usize); 3]) -> Self {
+        Self { map, offsets, rotations: [0; 3] }
+    }
+
+    /// Rotates the map by `0`, `90` and `180` degrees for the red, green and blue channels respectively.
+    ///
+    /// Maps that never repeat, such as `NoiseMap`, can't be rotated within a tile - use `PerChannel::offset`
+    /// for those instead.
+    pub fn rotated(map: M) -> Self {
+        Self { map, offsets: [(0, 0); 3], rotations: [0, 1, 2] }
+    }
+
+    /// Creates a clone of the wrapper with different rotations, as a number of quarter turns for each channel.
+    pub fn with_rotations(&self, rotations: [u8; 3]) -> Self where M: Clone {
+        Self { map: self.map.clone(), rotations, ..*self }
+    }
+}
+
+impl<M: ThresholdMap> ThresholdMap for PerChannel<M> {
+    fn threshold(&self, x: usize, y: usize) -> f32 {
+        self.map.threshold(x, y)
+    }
+
+    fn channel_threshold(&self, x: usize, y: usize, channel: usize) -> f32 {
+        let (dx, dy) = self.offsets[channel % 3];
+        let (x, y) = (x + dx, y + dy);
+
+        let (x, y) = match self.map.dimensions() {
+            Some((xdim, ydim)) => {
+                let (xdim, ydim) = (xdim.max(1), ydim.max(1));
+
+                match self.rotations[channel % 3] % 4 {
+                    0 => (x, y),
+                    1 => (y % xdim, ydim - 1 - x % ydim),
+                    2 => (xdim - 1 - x % xdim, ydim - 1 - y % ydim),
+                    _ => (xdim - 1 - y % xdim, x % ydim),
+                }
+            },
+            None => (x, y),
+        };
+
+        self.map.channel_threshold(x, y, channel)
+    }
+
+    fn dimensions(&self) -> Option<(usize, usize)> {
+        self.map.dimensions()
+    }
+}
+
+/// Represents _ordered_ dithering using any threshold map. Compared to error propagation, this method is less
+/// accurate - however it results in a pattern that can be visually appealing.
+///
+/// In addition it only modifies each pixel on its own without needing to simultaneously touch/affect other pixels,
+/// making it easily possible to parallellize.
+///
+/// Constructors such as `Ordered::bayer`, `Ordered::blue_noise` and `Ordered::white_noise` create this with their respective maps.
+#[derive(Clone)]
+pub struct Ordered<M: ThresholdMap> {
+    map: M,
+    palette: Vec<Srgb>,
+    bit_depth: Option<BitDepth>,
+    distance: ColourDistance,
+    colour_space: ColourSpace,
+    spread: Spread,
+}
+
+impl<M: ThresholdMap> Ordered<M> {
+    /// Creates a new `Ordered` ditherer with the given threshold map.
+    pub fn new(map: M, palette: Vec<Srgb>) -> Self {
+        Self {
+            map,
+            palette,
+            bit_depth: None,
+            distance: ColourDistance::WeightedRgb,
+            colour_space: ColourSpace::Srgb,
+            spread: Spread::Palette,
+        }
+    }
+
+    /// Creates a clone of the ditherer with a different threshold map, such as a Bayer matrix of another size.
+    pub fn with_map(&self, map: M) -> Self {
+        Self { map, palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that rounds each channel to the bit depth, such as `BitDepth::RGB565`,
+    /// instead of using the palette.
+    pub fn with_bit_depth(&self, bit_depth: BitDepth) -> Self where M: Clone {
+        Self { bit_depth: Some(bit_depth), map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different colour distance metric.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self where M: Clone {
+        Self { distance, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that applies the threshold offset in a different colour space.
+    ///
+    /// For example, `ColourSpace::LinearRgb` results in gamma-correct dithering. Note that the closest
+    /// colour is still measured using the configured `ColourDistance` - so for thresholds to split
+    /// evenly in linear light, pair it with `ColourDistance::LinearRgb`.
+    pub fn with_colour_space(&self, colour_space: ColourSpace) -> Self where M: Clone {
+        Self { colour_space, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that only offsets and measures the Rec. 709 luminance of each colour,
+    /// by setting both the colour space and the distance metric to their `Luminance` variants.
+    ///
+    /// Meant for palettes of grays, such as `palettes::grayscale`.
+    pub fn with_luminance(&self) -> Self where M: Clone {
+        self.with_colour_space(ColourSpace::Luminance).with_distance(ColourDistance::Luminance)
+    }
+
+    /// Creates a clone of the ditherer that only offsets and measures the CIE `L*` lightness of each colour,
+    /// by setting both the colour space and the distance metric to their `Lightness` variants.
+    ///
+    /// Meant for palettes of grays, such as `palettes::grayscale`.
+    pub fn with_lightness(&self) -> Self where M: Clone {
+        self.with_colour_space(ColourSpace::Lightness).with_distance(ColourDistance::Lightness)
+    }
+
+    /// Creates a clone of the ditherer with a different spread.
+    ///
+    /// By default the spread is derived from the palette, so that sparse palettes still dither while
+    /// dense palettes don't become overly noisy. `Spread::Fixed(1.0 / 3.0)` results in the original behaviour.
+    pub fn with_spread(&self, spread: Spread) -> Self where M: Clone {
+        Self { spread, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+}
+
+impl<M: ThresholdMap> Ordered<M> {
+    fn dither(&self, image: RgbImageRepr) -> (RgbImageRepr, Vec<Vec<usize>>) {
+        let palette = palette_matcher(&self.palette, self.bit_depth, self.distance);
+        let spread = self.spread.resolve(&palette, self.colour_space);
+        dither_ordered(image, &self.map, &palette, self.colour_space, spread)
+    }
+}
+
+impl<M: ThresholdMap> Effect<RgbImageRepr> for Ordered<M> {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        self.dither(image).0
+    }
+}
+
+impl<M: ThresholdMap> IndexedDither for Ordered<M> {
+    fn palette(&self) -> Vec<Srgb> {
+        indexed_palette(&self.palette, self.bit_depth)
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.dither(image).1
+    }
+}
+
+/// Prepares the palette for finding the closest colour - or the bit depth instead, if there is one.
+pub(crate) fn palette_matcher(palette: &[Srgb], bit_depth: Option<BitDepth>, distance: ColourDistance) -> PaletteMatcher {
+    match bit_depth {
+        Some(bit_depth) => PaletteMatcher::from_bit_depth(bit_depth),
+        None => PaletteMatcher::new(palette, distance),
+    }
+}
+
+/// Dithers the image by offsetting each pixel using the threshold map, before picking the closest palette colour.
+///
+/// Returns the palette index picked for each pixel alongside the image.
+pub(crate) fn dither_ordered(
+    mut image: RgbImageRepr,
+    map: &impl ThresholdMap,
+    palette: &PaletteMatcher,
+    space: ColourSpace,
+    spread: [f32; 3],
+) -> (RgbImageRepr, Vec<Vec<usize>>) {
+    let offset = |x, y, channel: usize| spread[channel] * (map.channel_threshold(x, y, channel) - 0.5);
+    let mut indices = vec![vec![0; image.first().map_or(0, Vec::len)]; image.len()];
+
+    for (y, row) in image.iter_mut().enumerate() {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let (c1, c2, c3) = space.from_srgb(Srgb::from(*pixel).into_format());
+
+            let colour = (
+                c1 + offset(x, y, 0),
+                c2 + offset(x, y, 1),
+                c3 + offset(x, y, 2),
+            );
+
+            let index = palette.nearest_index_in(colour, space);
+            *pixel = palette.colour(index).unwrap_or_else(|| space.to_srgb(colour)).into_format().into();
+            indices[y][x] = index;
+        }
+    }
+
+    (image, indices)
+}
+
+/// Dithers the image using a _plan_ for each colour - a list of palette indices sorted from dark to light,
+/// out of which the threshold map picks the one to draw.
+///
+/// Plans are only computed once per unique colour. Returns the palette index picked for each pixel alongside the image.
+pub(crate) fn dither_planned(
+    mut image: RgbImageRepr,
+    map: &impl ThresholdMap,
+    palette: &[Srgb],
+    plan: impl Fn(Srgb) -> Vec<usize>,
+) -> (RgbImageRepr, Vec<Vec<usize>>) {
+    let mut plans: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+    let mut indices = vec![vec![0; image.first().map_or(0, Vec::len)]; image.len()];
+
+    for (y, row) in image.iter_mut().enumerate() {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let plan = plans.entry(*pixel).or_insert_with(|| plan(Srgb::from(*pixel).into_format()));
+
+            if plan.is_empty() {
+                continue;
+            }
+
+            let threshold = map.threshold(x, y).clamp(0.0, 1.0);
+            let index = ((threshold * plan.len() as f32) as usize).min(plan.len() - 1);
+
+            *pixel = palette[plan[index]].into_format().into();
+            indices[y][x] = plan[index];
+        }
+    }
+
+    (image, indices)
+}
+
+/// The luminance of each palette colour, used for sorting plans.
+pub(crate) fn palette_luma(palette: &[Srgb]) -> Vec<f32> {
+    palette.iter()
+        .map(|colour| 0.299 * colour.red + 0.587 * colour.green + 0.114 * colour.blue)
+        .collect()
+}
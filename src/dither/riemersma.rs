@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+
+use palette::Srgb;
+
+use crate::{
+    utils::image::{get_dimensions_of_matrix, RgbImageRepr},
+    colour::{utils::PaletteMatcher, comparisons::ColourDistance, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::{alpha::MaskedDither, indexed::IndexedDither};
+
+/// Represents Riemersma dithering, which walks through the image along a Hilbert curve rather than row by row.
+///
+/// The curve is generalized to cover images of any size, rather than a square with a power of two as its side.
+///
+/// Instead of propagating error to neighbouring pixels, it keeps a _history_ of the most recent errors along the
+/// curve - each of them weighed less the older they are. Since the curve constantly changes direction, this avoids
+/// the directional artifacts that the error propagation algorithms have.
+#[derive(Clone)]
+pub struct Riemersma {
+    palette: Vec<Srgb>,
+    history: usize,
+    ratio: f32,
+    distance: ColourDistance,
+    colour_space: ColourSpace,
+}
+
+impl Riemersma {
+    /// Creates a new `Riemersma` ditherer with the given palette.
+    ///
+    /// Defaults to a history of `16` errors, with the newest error weighing `16` times more than the oldest.
+    pub fn new(palette: Vec<Srgb>) -> Self {
+        Self {
+            palette,
+            history: 16,
+            ratio: 16.0,
+            distance: ColourDistance::WeightedRgb,
+            colour_space: ColourSpace::Srgb,
+        }
+    }
+
+    /// Creates a clone of the ditherer that remembers a different amount of errors.
+    pub fn with_history(&self, history: usize) -> Self {
+        Self { history: history.max(1), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different ratio between the weights of the newest and the oldest error.
+    pub fn with_ratio(&self, ratio: f32) -> Self {
+        Self { ratio: ratio.max(1.0), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different colour distance metric.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self {
+        Self { distance, palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that computes error in a different colour space.
+    pub fn with_colour_space(&self, colour_space: ColourSpace) -> Self {
+        Self { colour_space, palette: self.palette.clone(), ..*self }
+    }
+
+    /// The weight of each error in the history, from the oldest to the newest.
+    fn weights(&self) -> Vec<f32> {
+        if self.history == 1 {
+            return vec![1.0];
+        }
+
+        (0..self.history)
+            .map(|i| self.ratio.powf(i as f32 / (self.history - 1) as f32) / self.ratio)
+            .collect()
+    }
+}
+
+/// Visits every point of a `width * height` grid in order along a _generalized Hilbert curve_, as described by
+/// Jakub Červený.
+///
+/// Unlike the classic Hilbert curve, this covers rectangles of any size without leaving them - so there are no
+/// points to skip outside of the grid. When the longer side is odd and the shorter side even, a single step of the
+/// curve is diagonal.
+pub(crate) fn gilbert_curve(width: usize, height: usize, visit: &mut impl FnMut(usize, usize)) {
+    let (width, height) = (width as isize, height as isize);
+
+    if width >= height {
+        gilbert_segment((0, 0), (width, 0), (0, height), visit);
+    } else {
+        gilbert_segment((0, 0), (0, height), (width, 0), visit);
+    }
+}
+
+/// Visits the rectangle at `start` along the curve, with `a` spanning its major axis and `b` its minor axis.
+fn gilbert_segment(start: (isize, isize), a: (isize, isize), b: (isize, isize), visit: &mut impl FnMut(usize, usize)) {
+    let (x, y) = start;
+    let (width, height) = ((a.0 + a.1).abs(), (b.0 + b.1).abs());
+    let (da, db) = ((a.0.signum(), a.1.signum()), (b.0.signum(), b.1.signum()));
+
+    if width == 1 || height == 1 {
+        let (steps, step) = if height == 1 { (width, da) } else { (height, db) };
+        for i in 0..steps {
+            visit((x + step.0 * i) as usize, (y + step.1 * i) as usize);
+        }
+        return;
+    }
+
+    let mut a2 = (a.0.div_euclid(2), a.1.div_euclid(2));
+    let mut b2 = (b.0.div_euclid(2), b.1.div_euclid(2));
+
+    if 2 * width > 3 * height {
+        // long rectangles are split in two along the major axis, keeping the halves even where possible
+        if (a2.0 + a2.1).abs() % 2 == 1 && width > 2 {
+            a2 = (a2.0 + da.0, a2.1 + da.1);
+        }
+
+        gilbert_segment((x, y), a2, b, visit);
+        gilbert_segment((x + a2.0, y + a2.1), (a.0 - a2.0, a.1 - a2.1), b, visit);
+    } else {
+        // others are split in three - up, across and back down - like the classic curve
+        if (b2.0 + b2.1).abs() % 2 == 1 && height > 2 {
+            b2 = (b2.0 + db.0, b2.1 + db.1);
+        }
+
+        gilbert_segment((x, y), b2, a2, visit);
+        gilbert_segment((x + b2.0, y + b2.1), a, (b.0 - b2.0, b.1 - b2.1), visit);
+        gilbert_segment(
+            (x + (a.0 - da.0) + (b2.0 - db.0), y + (a.1 - da.1) + (b2.1 - db.1)),
+            (-b2.0, -b2.1),
+            (-(a.0 - a2.0), -(a.1 - a2.1)),
+            visit,
+        );
+    }
+}
+
+impl Effect<RgbImageRepr> for Riemersma {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        self.walk(image, None).0
+    }
+}
+
+impl IndexedDither for Riemersma {
+    fn palette(&self) -> Vec<Srgb> {
+        self.palette.clone()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.walk(image, None).1
+    }
+}
+
+impl MaskedDither for Riemersma {
+    fn dither_masked(&self, image: RgbImageRepr, mask: &[Vec<bool>]) -> RgbImageRepr {
+        self.walk(image, Some(mask)).0
+    }
+}
+
+impl Riemersma {
+    /// Dithers the image along the curve, skipping the pixels that are excluded by the mask.
+    ///
+    /// Returns the palette index picked for each pixel alongside the image, with excluded pixels getting `0`.
+    fn walk(&self, mut image: RgbImageRepr, mask: Option<&[Vec<bool>]>) -> (RgbImageRepr, Vec<Vec<usize>>) {
+        let (xdim, ydim) = get_dimensions_of_matrix(&image);
+        let mut indices = vec![vec![0; xdim]; ydim];
+
+        if xdim == 0 || ydim == 0 || self.palette.is_empty() {
+            return (image, indices);
+        }
+
+        let space = self.colour_space;
+        let palette = PaletteMatcher::new(&self.palette, self.distance);
+        let palette_in_space: Vec<_> = self.palette.iter().map(|&colour| space.from_srgb(colour)).collect();
+
+        let weights = self.weights();
+        let mut errors = VecDeque::from(vec![(0.0, 0.0, 0.0); self.history]);
+
+        gilbert_curve(xdim, ydim, &mut |x, y| {
+            if mask.is_some_and(|mask| !mask[y][x]) {
+                return;
+            }
+
+            let original = space.from_srgb(Srgb::from(image[y][x]).into_format());
+
+            let error = errors.iter().zip(weights.iter()).fold((0.0, 0.0, 0.0), |acc, (err, weight)| (
+                acc.0 + err.0 * weight,
+                acc.1 + err.1 * weight,
+                acc.2 + err.2 * weight,
+            ));
+
+            let colour = (original.0 + error.0, original.1 + error.1, original.2 + error.2);
+            let index = palette.nearest_index_in(colour, space);
+            let quantized = palette_in_space[index];
+
+            image[y][x] = self.palette[index].into_format().into();
+            indices[y][x] = index;
+
+            errors.pop_front();
+            errors.push_back((
+                original.0 - quantized.0,
+                original.1 - quantized.1,
+                original.2 - quantized.2,
+            ));
+        });
+
+        (image, indices)
+    }
+}
@@ -0,0 +1,197 @@
+use palette::Srgb;
+
+use crate::{
+    utils::image::RgbImageRepr,
+    colour::{comparisons::ColourDistance, conversions::ColourSpace},
+    effect::Effect,
+};
+
+use super::{ordered::{ThresholdMap, dither_planned, palette_luma}, indexed::IndexedDither};
+
+type Colour = (f32, f32, f32);
+
+/// The variations of Yliluoma's ordered dithering algorithm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum YliluomaAlgorithm {
+    /// Mixes two palette colours per pixel, searching every pair and ratio for the closest mix.
+    ///
+    /// Slow for large palettes, since the search grows with the square of the palette size.
+    One,
+
+    /// Builds a plan out of multiple palette colours by repeatedly adding the colour that brings the
+    /// average closest to the input, then sorts the plan by luminance. This is the default.
+    #[default]
+    Two,
+}
+
+/// Represents Joel Yliluoma's _arbitrary-palette_ ordered dithering.
+///
+/// Rather than offsetting each pixel and snapping it to the closest colour like `Bayer`, it picks a _mixing plan_
+/// of palette colours whose average best approximates the pixel - and then uses the threshold map to decide which
+/// colour of the plan gets drawn. This avoids the banding and wrong mixes that offsetting causes with arbitrary palettes.
+///
+/// Plans are only computed once per unique colour, so images with fewer colours are much faster to dither.
+#[derive(Clone)]
+pub struct Yliluoma<M: ThresholdMap> {
+    map: M,
+    palette: Vec<Srgb>,
+    algorithm: YliluomaAlgorithm,
+    plan_size: usize,
+    distance: ColourDistance,
+    colour_space: ColourSpace,
+}
+
+impl<M: ThresholdMap> Yliluoma<M> {
+    /// Creates a new `Yliluoma` ditherer with the given threshold map, such as `ThresholdMatrix::bayer(8)`.
+    ///
+    /// The size of the mixing plan defaults to the amount of thresholds in the map, up to `64` - which
+    /// is also used for maps that never repeat.
+    pub fn new(map: M, palette: Vec<Srgb>) -> Self {
+        let thresholds = map.dimensions().map_or(64, |(xdim, ydim)| xdim * ydim);
+
+        Self {
+            map,
+            palette,
+            algorithm: YliluomaAlgorithm::Two,
+            plan_size: thresholds.clamp(2, 64),
+            distance: ColourDistance::WeightedRgb,
+            colour_space: ColourSpace::Srgb,
+        }
+    }
+
+    /// Creates a clone of the ditherer using a different variation of the algorithm.
+    pub fn with_algorithm(&self, algorithm: YliluomaAlgorithm) -> Self where M: Clone {
+        Self { algorithm, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different mixing plan size.
+    ///
+    /// For algorithm 1 this is the amount of possible ratios between the two colours, while for algorithm 2
+    /// it's the amount of colours in the plan.
+    pub fn with_plan_size(&self, plan_size: usize) -> Self where M: Clone {
+        Self { plan_size: plan_size.max(1), map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer with a different colour distance metric.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self where M: Clone {
+        Self { distance, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Creates a clone of the ditherer that mixes colours in a different colour space.
+    pub fn with_colour_space(&self, colour_space: ColourSpace) -> Self where M: Clone {
+        Self { colour_space, map: self.map.clone(), palette: self.palette.clone(), ..*self }
+    }
+
+    /// Compares a colour in the working space against an already prepared target.
+    fn penalty(&self, target: Colour, colour: Colour) -> f32 {
+        self.distance.compare(target, self.distance.prepare(self.colour_space.to_srgb(colour)))
+    }
+
+    /// Algorithm 1 - finds the best pair of colours and ratio, as a plan where the second colour comes first.
+    fn plan_pair(&self, colour: Srgb, palette: &[Colour]) -> Vec<usize> {
+        let target = self.distance.prepare(colour);
+        let levels = self.plan_size;
+
+        let mut best = (f32::MAX, 0, 0, 0);
+
+        for (i, first) in palette.iter().enumerate() {
+            for (j, second) in palette.iter().enumerate().skip(i) {
+                let spread = self.penalty(self.distance.prepare(self.colour_space.to_srgb(*first)), *second);
+
+                for ratio in 0..levels {
+                    let mix = ratio as f32 / levels as f32;
+                    let mixed = (
+                        first.0 + mix * (second.0 - first.0),
+                        first.1 + mix * (second.1 - first.1),
+                        first.2 + mix * (second.2 - first.2),
+                    );
+
+                    let penalty = self.penalty(target, mixed) + spread * 0.1 * ((mix - 0.5).abs() + 0.5);
+
+                    if penalty < best.0 {
+                        best = (penalty, i, j, ratio);
+                    }
+                }
+            }
+        }
+
+        let (_, first, second, ratio) = best;
+        let mut plan = vec![second; ratio];
+        plan.resize(levels, first);
+        plan
+    }
+
+    /// Algorithm 2 - repeatedly adds the colour that brings the plan's average closest to the target.
+    fn plan_mix(&self, colour: Srgb, palette: &[Colour], luma: &[f32]) -> Vec<usize> {
+        let target = self.distance.prepare(colour);
+        let mut plan: Vec<usize> = Vec::with_capacity(self.plan_size);
+        let mut sum = (0.0, 0.0, 0.0);
+
+        while plan.len() < self.plan_size {
+            let max_count = plan.len().max(1);
+            let mut best = (f32::MAX, 0, 1);
+
+            for (i, candidate) in palette.iter().enumerate() {
+                let mut count = 1;
+
+                // try adding the candidate 1, 2, 4... times
+                while count <= max_count && plan.len() + count <= self.plan_size {
+                    let total = (plan.len() + count) as f32;
+                    let average = (
+                        (sum.0 + candidate.0 * count as f32) / total,
+                        (sum.1 + candidate.1 * count as f32) / total,
+                        (sum.2 + candidate.2 * count as f32) / total,
+                    );
+
+                    let penalty = self.penalty(target, average);
+                    if penalty < best.0 {
+                        best = (penalty, i, count);
+                    }
+
+                    count *= 2;
+                }
+            }
+
+            let (_, chosen, count) = best;
+            let added = palette[chosen];
+            sum = (sum.0 + added.0 * count as f32, sum.1 + added.1 * count as f32, sum.2 + added.2 * count as f32);
+            plan.extend(std::iter::repeat_n(chosen, count));
+        }
+
+        plan.sort_by(|a, b| luma[*a].partial_cmp(&luma[*b]).unwrap_or(std::cmp::Ordering::Equal));
+        plan
+    }
+}
+
+impl<M: ThresholdMap> Yliluoma<M> {
+    fn dither(&self, image: RgbImageRepr) -> (RgbImageRepr, Vec<Vec<usize>>) {
+        if self.palette.is_empty() {
+            let indices = vec![vec![0; image.first().map_or(0, Vec::len)]; image.len()];
+            return (image, indices);
+        }
+
+        let palette: Vec<_> = self.palette.iter().map(|&colour| self.colour_space.from_srgb(colour)).collect();
+        let luma = palette_luma(&self.palette);
+
+        dither_planned(image, &self.map, &self.palette, |colour| match self.algorithm {
+            YliluomaAlgorithm::One => self.plan_pair(colour, &palette),
+            YliluomaAlgorithm::Two => self.plan_mix(colour, &palette, &luma),
+        })
+    }
+}
+
+impl<M: ThresholdMap> Effect<RgbImageRepr> for Yliluoma<M> {
+    fn affect(&self, image: RgbImageRepr) -> RgbImageRepr {
+        self.dither(image).0
+    }
+}
+
+impl<M: ThresholdMap> IndexedDither for Yliluoma<M> {
+    fn palette(&self) -> Vec<Srgb> {
+        self.palette.clone()
+    }
+
+    fn dither_indices(&self, image: RgbImageRepr) -> Vec<Vec<usize>> {
+        self.dither(image).1
+    }
+}
@@ -1,7 +1,7 @@
 
 use palette::Srgb;
 
-use crate::{utils::image::RgbPixelRepr, effect::Effect};
+use crate::{utils::image::RgbPixelRepr, effect::Effect, colour::{utils::PaletteMatcher, comparisons::ColourDistance}};
 
 use super::raw::{contrast, gradient_map, quantize_hue, brighten, saturate, shift_hue, multiply_hue};
 
@@ -101,6 +101,25 @@ impl QuantizeHue {
     }
 }
 
+/// Quantizes each pixel to the closest colour in the palette, without any dithering.
+///
+/// The closest colour is measured using `ColourDistance::WeightedRgb` unless configured otherwise.
+pub struct Quantize {
+    matcher: PaletteMatcher
+}
+
+impl Quantize {
+    /// Create a `Quantize` effect with the given palette.
+    pub fn new(palette: Vec<Srgb>) -> Self {
+        Self { matcher: PaletteMatcher::new(&palette, ColourDistance::WeightedRgb) }
+    }
+
+    /// Creates a clone of the effect with a different colour distance metric.
+    pub fn with_distance(&self, distance: ColourDistance) -> Self {
+        Self { matcher: PaletteMatcher::new(self.matcher.palette(), distance) }
+    }
+}
+
 /// Multiplies the hue of each pixel by the factor passed.
 pub struct MultiplyHue(pub f32);
 
@@ -143,6 +162,12 @@ impl Effect<RgbPixelRepr> for GradientMap {
     }
 }
 
+impl Effect<RgbPixelRepr> for Quantize {
+    fn affect(&self, item: RgbPixelRepr) -> RgbPixelRepr {
+        self.matcher.nearest(Srgb::from(item).into_format()).into_format().into()
+    }
+}
+
 impl Effect<RgbPixelRepr> for MultiplyHue {
     fn affect(&self, item: RgbPixelRepr) -> RgbPixelRepr {
         multiply_hue(item, self.0)
@@ -147,12 +147,13 @@ pub type GradientMap<'a, Color> = &'a [(Color, f32)];
 mod test {
     use std::error::Error;
 
-    use image::{DynamicImage, ImageResult, GenericImageView, imageops};
-    use palette::{Srgb, named};
+    use image::{DynamicImage, ImageResult, GenericImageView, imageops, Frame, RgbaImage, Rgba};
+    use palette::{Srgb, Lab, Lch, FromColor, named};
 
     use crate::{
-        colour::{utils::ONE_BIT},
-        prelude::{*, palettes::{WEB_SAFE, EIGHT_BIT}}, dither::{FLOYD_STEINBERG, JARVIS_JUDICE_NINKE, STUCKI, ATKINSON, BURKES, SIERRA, SIERRA_TWO_ROW, SIERRA_LITE, bayer::Bayer},
+        colour::{utils::{ONE_BIT, BitDepth, PaletteMatcher}, comparisons::{ColourDistance, cie94, ciede2000}, utils::{quantize_rgb, quantize_rgb_with}, conversions::{ColourSpace, rec_709_luminance}},
+        utils::image::{RgbImageRepr, RgbaImageRepr},
+        prelude::{*, palettes::{WEB_SAFE, EIGHT_BIT, ZX_SPECTRUM}}, dither::{FLOYD_STEINBERG, JARVIS_JUDICE_NINKE, STUCKI, ATKINSON, BURKES, SIERRA, SIERRA_TWO_ROW, SIERRA_LITE, OSTROMOUKHOV, ScanOrder, bayer::Bayer, riemersma::{Riemersma, gilbert_curve}, ordered::{Ordered, ThresholdMap, ThresholdMatrix, PerChannel, Spread}, yliluoma::{Yliluoma, YliluomaAlgorithm}, knoll::Knoll, noise::{NoiseDistribution, NoiseMap}, hybrid::Hybrid, edges::EdgeDetection, kernel::{Kernel, KernelError, KernelWarning}, dot_diffusion::DotDiffusion, direct_binary_search::DirectBinarySearch, animated::Animated, alpha::AlphaAware, indexed::{IndexedDither, IndexedImage, IndexedError}, attribute::{AttributeDither, AttributeLayout}},
     };
 
     type UtilResult<T> = Result<T,Box<dyn Error>>;
@@ -176,6 +177,19 @@ mod test {
         Ok(image)
     }
 
+    /// A ramp of grays from black to nearly white, going up by `256 / width` on every column.
+    fn gray_ramp(width: usize, height: usize) -> RgbImageRepr {
+        (0..height)
+            .map(|_| (0..width).map(|x| [(x * 256 / width) as u8; 3]).collect())
+            .collect()
+    }
+
+    /// The average value of one channel across the entire image.
+    fn channel_mean(image: &RgbImageRepr, channel: usize) -> f32 {
+        let total: usize = image.iter().flatten().map(|pixel| pixel[channel] as usize).sum();
+        total as f32 / (image.len() * image[0].len()) as f32
+    }
+
     #[test]
     fn dither_test() -> UtilResult<()> {
         let image = get_image()?;
@@ -190,9 +204,830 @@ mod test {
         dither(&image, EIGHT_BIT.to_vec(), Some("-8-bit"))?;
         dither(&image, palette, Some("-custom-palette"))?;
 
+        image.clone().apply(&FLOYD_STEINBERG.with_bit_depth(BitDepth::RGB332))
+            .save("data/dither/floyd-steinberg-rgb332.png")?;
+        image.clone().apply(&Ordered::bayer(8, Vec::new()).with_bit_depth(BitDepth::RGB332))
+            .save("data/dither/bayer-8x8-rgb332.png")?;
+        image.clone().apply(&FLOYD_STEINBERG
+            .with_luminance()
+            .with_palette(palettes::grayscale(4)))
+            .save("data/dither/floyd-steinberg-luminance-4-grays.png")?;
+        image.clone().apply(&AttributeDither::new(AttributeLayout::zx_spectrum()))
+            .save("data/dither/attribute-zx-spectrum.png")?;
+        image.clone().apply(&AttributeDither::new(AttributeLayout::c64_multicolour()))
+            .save("data/dither/attribute-c64-multicolour.png")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_propagation_preserves_tone_test() {
+        let ramp = gray_ramp(256, 32);
+
+        for propagator in [FLOYD_STEINBERG, OSTROMOUKHOV] {
+            let dithered = ramp.clone().apply(&propagator.with_palette(ONE_BIT.to_vec()));
+            assert!((channel_mean(&ramp, 0) - channel_mean(&dithered, 0)).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn error_propagation_controls_test() {
+        let image: RgbImageRepr = (0..16)
+            .map(|y| (0..64).map(|x| [x * 4, 255 - x * 4, y * 16]).collect())
+            .collect();
+
+        // without any error to propagate, each pixel is simply quantized
+        let quantized: RgbImageRepr = image.iter()
+            .map(|row| row.iter().map(|pixel| quantize_rgb(Srgb::from(*pixel).into_format(), &EIGHT_BIT).into_format().into()).collect())
+            .collect();
+        for propagator in [FLOYD_STEINBERG, JARVIS_JUDICE_NINKE, OSTROMOUKHOV] {
+            assert_eq!(image.clone().apply(&propagator.with_strength(0.0).with_palette(EIGHT_BIT.to_vec())), quantized);
+            assert_eq!(image.clone().apply(&propagator.with_error_limit(0.0).with_palette(EIGHT_BIT.to_vec())), quantized);
+        }
+
+        // with the error clamped to a tenth, a dark gray can never reach halfway to white
+        let flat: RgbImageRepr = vec![vec![[100; 3]; 64]; 64];
+        let whites = |image: RgbImageRepr| image.iter().flatten().filter(|pixel| pixel[0] == 255).count();
+        assert_eq!(whites(flat.clone().apply(&FLOYD_STEINBERG.with_error_limit(0.1).with_palette(ONE_BIT.to_vec()))), 0);
+        assert!(whites(flat.clone().apply(&FLOYD_STEINBERG.with_error_limit(0.3).with_palette(ONE_BIT.to_vec()))) > 0);
+
+        // light gray is in the palette, so it's left untouched despite the error of the darker grays around it
+        let palette = vec![Srgb::new(0.0, 0.0, 0.0), Srgb::from([200u8; 3]).into_format(), Srgb::new(1.0, 1.0, 1.0)];
+        let checkered: RgbImageRepr = (0..32)
+            .map(|y| (0..32).map(|x| if (x + y) % 4 == 0 { [200; 3] } else { [100; 3] }).collect())
+            .collect();
+        let is_kept = |(original, dithered): (&[u8; 3], &[u8; 3])| *original != [200; 3] || *dithered == [200; 3];
+
+        let preserved = checkered.clone().apply(&FLOYD_STEINBERG.with_preserve_exact_matches(true).with_palette(palette.clone()));
+        assert!(checkered.iter().flatten().zip(preserved.iter().flatten()).all(is_kept));
+
+        let dithered = checkered.clone().apply(&FLOYD_STEINBERG.with_palette(palette));
+        assert!(!checkered.iter().flatten().zip(dithered.iter().flatten()).all(is_kept));
+    }
+
+    #[test]
+    fn serpentine_scan_order_test() {
+        let image: RgbImageRepr = (0..16)
+            .map(|y| (0..64).map(|x| [x * 4, 255 - x * 4, y * 16]).collect())
+            .collect();
+        let mirror = |image: RgbImageRepr| -> RgbImageRepr {
+            image.into_iter().map(|row| row.into_iter().rev().collect()).collect()
+        };
+
+        // mirroring the image and flipping the direction of every row gives the mirrored output,
+        // which only holds if the kernel is mirrored on the rows traversed right-to-left
+        for propagator in [FLOYD_STEINBERG, JARVIS_JUDICE_NINKE, ATKINSON] {
+            let serpentine = image.clone().apply(&propagator.with_scan_order(ScanOrder::Serpentine).with_palette(EIGHT_BIT.to_vec()));
+            let flipped = mirror(image.clone()).apply(&propagator
+                .with_scan_order(ScanOrder::Custom(|y| y % 2 == 0))
+                .with_palette(EIGHT_BIT.to_vec()));
+
+            assert_eq!(serpentine, mirror(flipped));
+            assert_ne!(serpentine, image.clone().apply(&propagator.with_palette(EIGHT_BIT.to_vec())));
+        }
+    }
+
+    #[test]
+    fn colour_distance_test() {
+        let lch = |l: f32, a: f32, b: f32| {
+            let lch: Lch = Lch::from_color(Lab::new(l, a, b));
+            (lch.l, lch.chroma, lch.hue.into_positive_degrees())
+        };
+
+        // reference pairs and CIEDE2000 differences from Sharma, Wu and Dalal (2005)
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((50.0, 2.5, 0.0), (61.0, -5.0, 29.0), 22.8977),
+            ((50.0, 2.5, 0.0), (56.0, -27.0, -3.0), 31.9030),
+            ((50.0, 2.5, 0.0), (58.0, 24.0, 15.0), 19.4535),
+            ((50.0, 2.5, 0.0), (50.0, 3.1736, 0.5854), 1.0000),
+            ((60.2574, -34.0099, 36.2677), (60.4626, -34.1751, 39.4387), 1.2644),
+            ((63.0109, -31.0961, -5.8663), (62.8187, -29.7946, -4.0864), 1.2630),
+            ((61.2901, 3.7196, -5.3901), (61.4292, 2.2480, -4.9620), 1.8731),
+        ];
+
+        for ((l_1, a_1, b_1), (l_2, a_2, b_2), expected) in pairs {
+            let distance = ciede2000(lch(l_1, a_1, b_1), lch(l_2, a_2, b_2));
+            assert!((distance - expected).abs() < 1e-3, "expected {}, got {}", expected, distance);
+        }
+
+        // CIE94 with the graphic arts constants, taking the first colour as the reference
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 1.3950),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.2361),
+            ((50.0, 2.5, 0.0), (50.0, 3.1736, 0.5854), 0.8221),
+        ];
+
+        for ((l_1, a_1, b_1), (l_2, a_2, b_2), expected) in pairs {
+            let distance = cie94(lch(l_1, a_1, b_1), lch(l_2, a_2, b_2));
+            assert!((distance - expected).abs() < 1e-3, "expected {}, got {}", expected, distance);
+        }
+
+        // hues on either side of 0° are close, and the hue of a gray doesn't matter
+        assert!(cie94((50.0, 10.0, 359.0), (50.0, 10.0, 1.0)) < 0.5);
+        assert!(cie94((50.0, 0.0, 0.0), (50.0, 0.0, 180.0)) < 1e-3);
+
+        // the metric decides which palette colour is the closest
+        let azure = Srgb::new(0.0, 0.6, 1.0);
+        let nearest = |distance: ColourDistance| -> [u8; 3] {
+            quantize_rgb_with(azure, &EIGHT_BIT, distance).into_format().into()
+        };
+        assert_eq!(nearest(ColourDistance::WeightedRgb), [0, 255, 255]);
+        assert_eq!(nearest(ColourDistance::Cie76), [255, 255, 255]);
+        assert_eq!(nearest(ColourDistance::Oklab), [0, 0, 255]);
+
+        let flat: RgbImageRepr = vec![vec![[0, 153, 255]; 8]; 8];
+        let quantized = flat.apply(&FLOYD_STEINBERG.with_strength(0.0).with_distance(ColourDistance::Oklab).with_palette(EIGHT_BIT.to_vec()));
+        assert!(quantized.iter().flatten().all(|pixel| *pixel == [0, 0, 255]));
+    }
+
+    #[test]
+    fn riemersma_test() {
+        let ramp = gray_ramp(256, 32);
+
+        for riemersma in [Riemersma::new(ONE_BIT.to_vec()), Riemersma::new(ONE_BIT.to_vec()).with_history(32).with_ratio(4.0)] {
+            let dithered = ramp.clone().apply(&riemersma);
+            assert!(dithered.iter().flatten().all(|pixel| *pixel == [0; 3] || *pixel == [255; 3]));
+            assert!((channel_mean(&ramp, 0) - channel_mean(&dithered, 0)).abs() < 1.0);
+        }
+
+        // the curve covers images of any size exactly once, moving to a neighbouring pixel at every step -
+        // diagonally at most once, which is unavoidable when the longer side is odd and the shorter side even
+        for (width, height) in [(1, 1), (1, 7), (5, 3), (3, 5), (8, 8), (17, 6), (6, 17), (31, 2), (100, 37)] {
+            let mut points = Vec::new();
+            gilbert_curve(width, height, &mut |x, y| points.push((x, y)));
+
+            let mut visited = points.clone();
+            visited.sort();
+            visited.dedup();
+            assert_eq!(visited.len(), width * height);
+            assert!(points.iter().all(|(x, y)| *x < width && *y < height));
+            let steps: Vec<(usize, usize)> = points.windows(2)
+                .map(|step| (step[0].0.abs_diff(step[1].0), step[0].1.abs_diff(step[1].1)))
+                .collect();
+            assert!(steps.iter().all(|(dx, dy)| (*dx).max(*dy) == 1));
+            assert!(steps.iter().filter(|(dx, dy)| dx + dy == 2).count() <= 1);
+        }
+
+        // and odd sizes are dithered too
+        let odd = gray_ramp(255, 17);
+        let dithered = odd.clone().apply(&Riemersma::new(ONE_BIT.to_vec()));
+        assert!((channel_mean(&odd, 0) - channel_mean(&dithered, 0)).abs() < 1.0);
+    }
+
+    #[test]
+    fn yliluoma_test() {
+        // a tile of the map draws every colour of the plan once, so it averages back to the input
+        let cases = [
+            (YliluomaAlgorithm::One, palettes::grayscale(5), [[32; 3], [96; 3], [160; 3]]),
+            (YliluomaAlgorithm::Two, EIGHT_BIT.to_vec(), [[64; 3], [200, 100, 50], [30, 160, 220]]),
+        ];
+
+        for (algorithm, palette, colours) in cases {
+            for colour in colours {
+                let flat: RgbImageRepr = vec![vec![colour; 8]; 8];
+                let dithered = flat.apply(&Yliluoma::new(ThresholdMatrix::bayer(8), palette.clone()).with_algorithm(algorithm));
+
+                assert!(dithered.iter().flatten().any(|pixel| *pixel != dithered[0][0]));
+                for (channel, value) in colour.into_iter().enumerate() {
+                    assert!((channel_mean(&dithered, channel) - value as f32).abs() < 2.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hybrid_dithering_test() {
+        let ramp = gray_ramp(256, 32);
+
+        for propagator in [FLOYD_STEINBERG, JARVIS_JUDICE_NINKE, SIERRA_LITE, OSTROMOUKHOV] {
+            let propagator = propagator.with_palette(ONE_BIT.to_vec());
+
+            // without any modulation, it's the same as the propagator on its own
+            let hybrid = Hybrid::new(propagator.clone(), ThresholdMatrix::bayer(8)).with_amount(0.0);
+            assert_eq!(ramp.clone().apply(&hybrid), ramp.clone().apply(&propagator));
+
+            let dithered = [
+                ramp.clone().apply(&Hybrid::new(propagator.clone(), ThresholdMatrix::bayer(8))),
+                ramp.clone().apply(&Hybrid::new(propagator.clone(), ThresholdMatrix::blue_noise(16, 0)).with_amount(1.0)),
+                ramp.clone().apply(&Hybrid::new(propagator.clone(), NoiseMap::interleaved_gradient(0))),
+            ];
+
+            for dithered in dithered.iter() {
+                assert_ne!(*dithered, ramp.clone().apply(&propagator));
+                assert!((channel_mean(&ramp, 0) - channel_mean(dithered, 0)).abs() < 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn kernel_test() -> UtilResult<()> {
+        let floyd_steinberg = Kernel::from(&FLOYD_STEINBERG);
+
+        assert_eq!(Kernel::parse("- x 7 / 5 3 1")?.with_name("floyd-steinberg"), floyd_steinberg);
+        assert_eq!("- x 7\n5 3 1".parse::<Kernel>()?.with_name("floyd-steinberg"), floyd_steinberg);
+        assert_eq!(floyd_steinberg.to_string(), "- x 7\n5 3 1");
+
+        for propagator in [FLOYD_STEINBERG, JARVIS_JUDICE_NINKE, STUCKI, ATKINSON, BURKES, SIERRA, SIERRA_TWO_ROW, SIERRA_LITE] {
+            let kernel = Kernel::from(&propagator);
+            let parsed = Kernel::parse(&kernel.to_string())?.with_name(propagator.name).with_portions(propagator.portions);
+            assert_eq!(parsed, kernel);
+        }
+
+        assert_eq!(Kernel::parse("- - 7 / 3 5 1"), Err(KernelError::MissingMarker));
+        assert_eq!(Kernel::parse("x x / 1 1"), Err(KernelError::MultipleMarkers));
+        assert_eq!(Kernel::parse("- x 7 / 3 5"), Err(KernelError::UnevenRows { row: 1 }));
+        assert_eq!(Kernel::parse("3 x 7"), Err(KernelError::PropagatesBackwards { row: 0, column: 0 }));
+        assert!(matches!(Kernel::parse("- x a"), Err(KernelError::InvalidEntry { row: 0, column: 2, .. })));
+
+        assert_eq!(floyd_steinberg.warnings(), vec![]);
+        assert_eq!(Kernel::from(&ATKINSON).warnings(), vec![KernelWarning::UnderPropagates(0.75)]);
+        assert_eq!(floyd_steinberg.with_portions(8).warnings(), vec![KernelWarning::OverPropagates(2.0)]);
+        assert_eq!(Kernel::parse("x")?.warnings(), vec![KernelWarning::Empty]);
+
+        let json = r#"{ "name": "floyd-steinberg", "rows": [["-", "x", 7], [5, 3, 1]], "portions": 16 }"#;
+        assert_eq!(Kernel::from_json(json)?, floyd_steinberg);
+        let json = r#"{ "name": "atkinson", "kernel": "- x 1 1 / 1 1 1 - / - 1 - -", "portions": 8 }"#;
+        assert_eq!(Kernel::from_json(json)?, Kernel::from(&ATKINSON));
+        assert!(matches!(Kernel::from_json(r#"{ "rows": [[1, 2]] }"#), Err(KernelError::MissingMarker)));
+        assert!(matches!(Kernel::from_json(r#"{ "kernel": 7 }"#), Err(KernelError::Json(_))));
+        assert!(matches!(Kernel::from_json(r#"{ "kernel": "x 1", "portions": 0 }"#), Err(KernelError::NoPortions)));
+        assert!(matches!(Kernel::from_json("{"), Err(KernelError::Json(_))));
+
+        let ramp = gray_ramp(256, 16);
+        let kernel = Kernel::parse("- x 7 / 5 3 1")?;
+
+        assert_eq!(
+            ramp.clone().apply(&kernel.propagator().with_palette(ONE_BIT.to_vec())),
+            ramp.clone().apply(&FLOYD_STEINBERG.with_palette(ONE_BIT.to_vec())),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn edge_aware_dithering_test() {
+        let region = |value: u8| -> RgbImageRepr { vec![vec![[value; 3]; 32]; 32] };
+        let (left, right) = (region(100), region(156));
+        let image: RgbImageRepr = left.iter().zip(right.iter()).map(|(left, right)| [left.clone(), right.clone()].concat()).collect();
+
+        let propagator = FLOYD_STEINBERG.with_palette(ONE_BIT.to_vec());
+
+        // fully attenuated, each region is dithered as if it was on its own - since error moving along the edge
+        // on either side of it is left alone
+        for propagator in [FLOYD_STEINBERG, JARVIS_JUDICE_NINKE].map(|propagator| propagator.with_palette(ONE_BIT.to_vec())) {
+            let separate: RgbImageRepr = left.clone().apply(&propagator).into_iter().zip(right.clone().apply(&propagator))
+                .map(|(left, right)| [left, right].concat())
+                .collect();
+
+            for detection in [EdgeDetection::Sobel(0.1), EdgeDetection::Difference(0.1)] {
+                let dithered = image.clone().apply(&propagator.with_edge_detection(detection, 0.0));
+                assert_eq!(dithered, separate);
+            }
+        }
+
+        // without any attenuation, nothing changes
+        for detection in [EdgeDetection::Sobel(0.1), EdgeDetection::Difference(0.1)] {
+            let dithered = image.clone().apply(&propagator.with_edge_detection(detection, 1.0));
+            assert_eq!(dithered, image.clone().apply(&propagator));
+        }
+
+        let dithered = image.clone().apply(&propagator.with_edge_detection(EdgeDetection::Sobel(0.1), 0.0));
+        assert_ne!(dithered, image.clone().apply(&propagator));
+
+        // with every gray in the palette, dithering leaves only the sharpening
+        let grays = FLOYD_STEINBERG.with_palette(palettes::grayscale(256));
+        assert_eq!(region(100).apply(&grays.with_sharpening(1.0)), region(100));
+
+        let sharpened = image.clone().apply(&grays.with_sharpening(1.0));
+        assert!(sharpened[16][31][0] < 100 && sharpened[16][32][0] > 156);
+        assert_eq!((sharpened[16][0][0], sharpened[16][63][0]), (100, 156));
+    }
+
+    #[test]
+    fn attribute_clash_test() -> UtilResult<()> {
+        let gradient = |width: usize, height: usize| -> RgbImageRepr {
+            (0..height)
+                .map(|y| (0..width).map(|x| [(x * 255 / width) as u8, (y * 255 / height) as u8, 128]).collect())
+                .collect()
+        };
+        // far fewer distinct colours per cell, to keep trying every subset cheap
+        let steps = |width: usize, height: usize| -> RgbImageRepr {
+            gradient(width, height).into_iter()
+                .map(|row| row.into_iter().map(|pixel| pixel.map(|channel| channel & 0xE0)).collect())
+                .collect()
+        };
+
+        // every cell uses its two colours, which are either both normal or both bright
+        let zx = AttributeDither::new(AttributeLayout::zx_spectrum());
+        let image = zx.dither_cells(steps(256, 192))?;
+        for (x, y) in (0..192).flat_map(|y| (0..256).map(move |x| (x, y))) {
+            assert!(image.cell(x, y).contains(&image.index(x, y)));
+        }
+        for cell in image.cells() {
+            assert_eq!(cell.len(), 2);
+            assert!(cell.iter().all(|&index| index < 8) || cell.iter().all(|&index| index >= 8));
+        }
+        assert_eq!(image.to_rgb(), steps(256, 192).apply(&zx));
+
+        let mut scr = Vec::new();
+        image.write_scr(&mut scr)?;
+        assert_eq!(scr.len(), 6912);
+
+        // the first line of the middle third starts at 0x800, and its cell is the first of the ninth row
+        let ink = image.cell(0, 64)[0];
+        for i in 0..8 {
+            assert_eq!(scr[0x800] & (0x80 >> i) != 0, image.index(i, 64) == ink);
+        }
+        assert_eq!(scr[6144 + 8 * 32] & 0x07, (ink % 8) as u8);
+
+        // cells already drawn with two colours of the same brightness are left as-is
+        let indices = |x: usize, y: usize| {
+            let (column, row) = (x / 8, y / 8);
+            let bright = (column + row) % 2 * 8;
+            let (a, b) = (1 + column % 7, 1 + (column + 1) % 7);
+            bright + if (x + y) % 2 == 0 { a } else { b }
+        };
+        let blocks: RgbImageRepr = (0..192)
+            .map(|y| (0..256).map(|x| ZX_SPECTRUM[indices(x, y)].into_format().into()).collect())
+            .collect();
+        assert_eq!(blocks.clone().apply(&zx), blocks);
+        assert!(zx.dither_cells(blocks)?.write_scr(Vec::new()).is_ok());
+        assert!(zx.dither_cells(gradient(64, 64))?.write_scr(Vec::new()).is_err());
+
+        // without a palette, the image is left as-is
+        let empty = AttributeDither::new(AttributeLayout::new(Vec::new(), (8, 8), 2));
+        assert_eq!(gradient(64, 64).apply(&empty), gradient(64, 64));
+        assert_eq!(empty.dither_indexed(gradient(64, 64)), Err(IndexedError::EmptyPalette));
+        assert!(matches!(empty.dither_cells(gradient(64, 64)), Err(IndexedError::EmptyPalette)));
+
+        // double-wide pixels, with three colours per cell on top of the background
+        let c64 = AttributeDither::new(AttributeLayout::c64_multicolour());
+        let image = c64.dither_cells(steps(320, 200))?;
+        let background = image.background().unwrap();
+        assert_eq!(image.dimensions(), (160, 200));
+        for (x, y) in (0..200).flat_map(|y| (0..160).map(move |x| (x, y))) {
+            let index = image.index(x, y);
+            assert!(index == background || image.cell(x, y).contains(&index));
+        }
+        for row in image.to_rgb() {
+            assert_eq!(row.len(), 320);
+            assert!(row.chunks(2).all(|pair| pair[0] == pair[1]));
+        }
+
+        let mut koala = Vec::new();
+        image.write_koala(&mut koala)?;
+        assert_eq!(koala.len(), 10003);
+        assert_eq!((koala[0], koala[1], koala[10002]), (0x00, 0x60, background as u8));
+
+        // every cell uses one of the four shared sub-palettes
+        let image = AttributeDither::new(AttributeLayout::nes()).dither_cells(gradient(48, 32))?;
+        let mut subsets = image.cells().to_vec();
+        subsets.sort();
+        subsets.dedup();
+        assert!(subsets.len() <= 4);
+        for (x, y) in (0..32).flat_map(|y| (0..48).map(move |x| (x, y))) {
+            let index = image.index(x, y);
+            assert!(Some(index) == image.background() || image.cell(x, y).contains(&index));
+        }
+
         Ok(())
     }
 
+    #[test]
+    fn dot_diffusion_test() {
+        let ramp = gray_ramp(256, 32);
+
+        let dithered = ramp.clone().apply(&DotDiffusion::new(ONE_BIT.to_vec()));
+        assert!((channel_mean(&ramp, 0) - channel_mean(&dithered, 0)).abs() < 4.0);
+
+        // a single class has nothing to diffuse to, so it's the same as quantizing
+        let quantized = ramp.clone().apply(&DotDiffusion::new(ONE_BIT.to_vec()).with_class_matrix(vec![vec![0]]));
+        for (original, quantized) in ramp.iter().flatten().zip(quantized.iter().flatten()) {
+            assert_eq!(quantized[0], if original[0] < 128 { 0 } else { 255 });
+        }
+    }
+
+    #[test]
+    fn direct_binary_search_test() {
+        let ramp = gray_ramp(128, 16);
+
+        let initial = Ordered::bayer(4, ONE_BIT.to_vec());
+        let search = DirectBinarySearch::new(initial.clone(), ONE_BIT.to_vec());
+
+        assert_eq!(ramp.clone().apply(&search.with_max_iterations(0)), ramp.clone().apply(&initial));
+
+        let dithered = ramp.clone().apply(&search);
+        assert!((channel_mean(&ramp, 0) - channel_mean(&dithered, 0)).abs() < 4.0);
+        assert_ne!(dithered, ramp.clone().apply(&initial));
+    }
+
+    #[test]
+    fn animated_dithering_test() {
+        // a gradient with a small square moving across it
+        let frames: Vec<Frame> = (0..4)
+            .map(|i| Frame::new(RgbaImage::from_fn(64, 64, |x, y| {
+                if (i * 8..i * 8 + 4).contains(&x) && (48..52).contains(&y) {
+                    Rgba([255, 0, 0, 255])
+                } else {
+                    Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255])
+                }
+            })))
+            .collect();
+
+        let ditherer = FLOYD_STEINBERG.with_palette(ONE_BIT.to_vec());
+        let independent: Vec<Frame> = frames.clone().into_iter().map(|frame| frame.apply(&ditherer)).collect();
+        let animated = frames.apply(&Animated::new(ditherer));
+
+        // everything above the square stays the same either way, but only the animated output keeps the rest stable
+        let differences = |frames: &[Frame]| frames.windows(2)
+            .map(|pair| pair[0].buffer().pixels().zip(pair[1].buffer().pixels()).filter(|(a, b)| a != b).count())
+            .sum::<usize>();
+
+        assert!(differences(&animated) <= 3 * 2 * 16);
+        assert!(differences(&independent) > differences(&animated));
+        assert_eq!(animated[0].buffer(), independent[0].buffer());
+    }
+
+    #[test]
+    fn alpha_aware_dithering_test() {
+        // a gray square on the right, with a transparent red background on the left
+        let image: RgbaImageRepr = (0..32)
+            .map(|_| (0..64).map(|x| if x < 32 { [255, 0, 0, 0] } else { [100, 100, 100, 255] }).collect())
+            .collect();
+
+        let ditherer = FLOYD_STEINBERG.with_palette(ONE_BIT.to_vec());
+        let dithered = image.clone().apply(&AlphaAware::new(ditherer.clone()).with_transparent_colour(Srgb::new(1.0, 0.0, 1.0)));
+
+        // the transparent background doesn't take or give any error, as if it weren't there at all
+        let square: RgbImageRepr = image.iter().map(|row| row[32..].iter().map(|[r, g, b, _]| [*r, *g, *b]).collect()).collect();
+        let square = square.apply(&ditherer);
+
+        for (row, expected) in dithered.iter().zip(square.iter()) {
+            assert!(row[..32].iter().all(|pixel| pixel == &[255, 0, 255, 0]));
+            assert!(row[32..].iter().zip(expected.iter()).all(|(pixel, expected)| pixel[..3] == expected[..] && pixel[3] == 255));
+        }
+
+        // without a transparent colour the background keeps its colour, even with ditherers that dither every pixel
+        let ordered = Ordered::bayer(8, ONE_BIT.to_vec());
+        let dithered = image.clone().apply(&AlphaAware::new(ordered.clone()));
+        let rgb: RgbImageRepr = image.iter().map(|row| row.iter().map(|[r, g, b, _]| [*r, *g, *b]).collect()).collect();
+        let expected = rgb.apply(&ordered);
+
+        for (row, expected) in dithered.iter().zip(expected.iter()) {
+            assert!(row[..32].iter().all(|pixel| pixel == &[255, 0, 0, 0]));
+            assert!(row[32..].iter().zip(expected[32..].iter()).all(|(pixel, expected)| pixel[..3] == expected[..] && pixel[3] == 255));
+        }
+
+        // alpha gets dithered down to 1-bit, while keeping roughly the same coverage
+        let faded: RgbaImageRepr = vec![vec![[0, 0, 0, 64]; 64]; 64];
+        let dithered = faded.apply(&AlphaAware::new(ditherer).with_alpha_levels(2));
+        let opaque = dithered.iter().flatten().filter(|pixel| pixel[3] == 255).count();
+
+        assert!(dithered.iter().flatten().all(|pixel| pixel[3] == 0 || pixel[3] == 255));
+        assert!(opaque.abs_diff(64 * 64 / 4) < 128);
+    }
+
+    #[test]
+    fn indexed_output_test() -> UtilResult<()> {
+        // an odd width, so that packed rows don't line up with bytes
+        let ramp: RgbImageRepr = (0..16)
+            .map(|y| (0..61).map(|x| [x * 4, 255 - x * 4, y * 16]).collect())
+            .collect();
+
+        let decode = |png: &[u8]| -> UtilResult<(png::BitDepth, bool, RgbaImageRepr)> {
+            let reader = png::Decoder::new(png).read_info()?;
+            let info = reader.info();
+            let (depth, has_trns) = (info.bit_depth, info.trns.is_some());
+            assert_eq!(info.color_type, png::ColorType::Indexed);
+
+            let image = image::load_from_memory(png)?.to_rgba8();
+            let pixels = image.rows().map(|row| row.map(|pixel| pixel.0).collect()).collect();
+            Ok((depth, has_trns, pixels))
+        };
+
+        let palettes = [
+            (ONE_BIT.to_vec(), png::BitDepth::One),
+            (palettes::grayscale(4), png::BitDepth::Two),
+            (palettes::grayscale(16), png::BitDepth::Four),
+            (WEB_SAFE.to_vec(), png::BitDepth::Eight),
+        ];
+
+        for (palette, depth) in palettes {
+            let ditherers: [Box<dyn IndexedDither>; 2] = [
+                Box::new(FLOYD_STEINBERG.with_palette(palette.clone())),
+                Box::new(Ordered::bayer(8, palette.clone())),
+            ];
+
+            for ditherer in ditherers.iter() {
+                let indexed = ditherer.dither_indexed(ramp.clone())?;
+                let dithered = ditherer.affect(ramp.clone());
+
+                assert_eq!(indexed.palette().len(), palette.len());
+                assert_eq!(indexed.dimensions(), (61, 16));
+
+                let rgb: RgbImageRepr = indexed.to_rgba().iter()
+                    .map(|row| row.iter().map(|[r, g, b, _]| [*r, *g, *b]).collect())
+                    .collect();
+                assert_eq!(rgb, dithered);
+
+                let mut png = Vec::new();
+                indexed.write_png(&mut png)?;
+                assert_eq!(decode(&png)?, (depth, false, indexed.to_rgba()));
+            }
+        }
+
+        // the indices are the ones each ditherer picked, even when the palette repeats a colour
+        let palette = [ONE_BIT.to_vec(), vec![Srgb::new(0.0, 0.0, 0.0)]].concat();
+        let ditherers: [Box<dyn IndexedDither>; 9] = [
+            Box::new(FLOYD_STEINBERG.with_palette(palette.clone())),
+            Box::new(Ordered::bayer(8, palette.clone())),
+            Box::new(Yliluoma::new(ThresholdMatrix::bayer(8), palette.clone())),
+            Box::new(Knoll::new(ThresholdMatrix::bayer(8), palette.clone())),
+            Box::new(Riemersma::new(palette.clone())),
+            Box::new(DotDiffusion::new(palette.clone())),
+            Box::new(Hybrid::new(FLOYD_STEINBERG.with_palette(palette.clone()), ThresholdMatrix::bayer(8))),
+            Box::new(DirectBinarySearch::new(Ordered::bayer(8, palette.clone()), palette.clone())),
+            Box::new(AttributeDither::new(AttributeLayout::zx_spectrum())),
+        ];
+
+        for ditherer in ditherers.iter() {
+            let indices = ditherer.dither_indices(ramp.clone());
+            let colours: Vec<[u8; 3]> = ditherer.palette().iter().map(|colour| colour.into_format().into()).collect();
+
+            let rgb: RgbImageRepr = indices.iter()
+                .map(|row| row.iter().map(|index| colours[*index]).collect())
+                .collect();
+            assert_eq!(rgb, ditherer.affect(ramp.clone()));
+
+            let indexed: Vec<u8> = indices.iter().flatten().map(|index| *index as u8).collect();
+            assert_eq!(ditherer.dither_indexed(ramp.clone())?.indices(), indexed);
+        }
+
+        // palettes that don't fit in an indexed image are an error, rather than a panic
+        let too_many = Ordered::bayer(8, Vec::new()).with_bit_depth(BitDepth::RGB565);
+        assert_eq!(too_many.dither_indexed(ramp.clone()), Err(IndexedError::TooManyColours(65536)));
+        let gradient = named::PURPLE.into_format().build_gradient_lch(300);
+        assert_eq!(FLOYD_STEINBERG.with_palette(gradient.clone()).dither_indexed(ramp.clone()), Err(IndexedError::TooManyColours(gradient.len())));
+        assert_eq!(Riemersma::new(Vec::new()).dither_indexed(ramp.clone()), Err(IndexedError::EmptyPalette));
+        assert!(matches!(IndexedImage::from_rgb(&ramp, ONE_BIT), Err(IndexedError::TooManyColours(_))));
+
+        // a gray square on the right, with a transparent red background on the left
+        let image: RgbaImageRepr = (0..16)
+            .map(|_| (0..32).map(|x| if x < 16 { [255, 0, 0, 0] } else { [100, 100, 100, 255] }).collect())
+            .collect();
+
+        let ditherer = AlphaAware::new(FLOYD_STEINBERG.with_palette(ONE_BIT.to_vec()))
+            .with_transparent_colour(Srgb::new(1.0, 0.0, 1.0));
+        let indexed = ditherer.dither_indexed(image)?;
+
+        assert_eq!(indexed.palette().len(), 3);
+        assert!((0..16).all(|y| (0..16).all(|x| indexed.index(x, y) == 2)));
+
+        let mut png = Vec::new();
+        indexed.write_png(&mut png)?;
+        assert_eq!(decode(&png)?, (png::BitDepth::Two, true, indexed.to_rgba()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bit_depth_test() {
+        let ramp: RgbImageRepr = (0..32)
+            .map(|y| (0..=255).map(|x| [x, 255 - x, y * 8]).collect())
+            .collect();
+
+        assert_eq!(BitDepth::RGB565.palette().len(), 65536);
+
+        for depth in [BitDepth::RGB565, BitDepth::RGB444, BitDepth::RGB332] {
+            let dithered = [
+                ramp.clone().apply(&FLOYD_STEINBERG.with_bit_depth(depth)),
+                ramp.clone().apply(&Ordered::bayer(8, Vec::new()).with_bit_depth(depth)),
+            ];
+
+            for dithered in dithered.iter() {
+                assert!(dithered.iter().flatten().all(|pixel| depth.contains(*pixel)));
+
+                for channel in 0..3 {
+                    assert!((channel_mean(&ramp, channel) - channel_mean(dithered, channel)).abs() < 2.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn luminance_dithering_test() {
+        let colours = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0], [128, 0, 255]];
+
+        for colour in colours {
+            let flat: RgbImageRepr = vec![vec![colour; 64]; 64];
+            let luminance = rec_709_luminance(Srgb::from(colour).into_format());
+
+            let dithered = flat.clone().apply(&FLOYD_STEINBERG.with_luminance().with_palette(ONE_BIT.to_vec()));
+
+            let whites = dithered.iter().flatten().filter(|pixel| **pixel == [255; 3]).count();
+            assert!((whites as f32 / (64 * 64) as f32 - luminance).abs() < 0.01);
+        }
+
+        let ramp: RgbImageRepr = (0..32)
+            .map(|y| (0..=255).map(|x| [x, 255 - x, y * 8]).collect())
+            .collect();
+
+        let grays: Vec<[u8; 3]> = palettes::grayscale(4).iter().map(|colour| colour.into_format().into()).collect();
+        assert_eq!(grays, vec![[0; 3], [85; 3], [170; 3], [255; 3]]);
+
+        let dithered = ramp.clone().apply(&FLOYD_STEINBERG.with_lightness().with_palette(palettes::grayscale(4)));
+        assert!(dithered.iter().flatten().all(|pixel| grays.contains(pixel)));
+
+        // the builders set the colour space and the distance together
+        assert_eq!(dithered, ramp.clone().apply(&FLOYD_STEINBERG
+            .with_colour_space(ColourSpace::Lightness)
+            .with_distance(ColourDistance::Lightness)
+            .with_palette(palettes::grayscale(4))));
+
+        let dithered = ramp.clone().apply(&Ordered::bayer(8, palettes::grayscale(4)).with_luminance());
+        assert!(dithered.iter().flatten().all(|pixel| grays.contains(pixel)));
+        assert_eq!(dithered, ramp.apply(&Ordered::bayer(8, palettes::grayscale(4))
+            .with_colour_space(ColourSpace::Luminance)
+            .with_distance(ColourDistance::Luminance)));
+    }
+
+    #[test]
+    fn perceptual_colour_space_test() {
+        let colours: [[u8; 3]; 6] = [[0, 0, 0], [255, 255, 255], [255, 0, 0], [12, 200, 97], [70, 30, 220], [128, 128, 128]];
+
+        for space in [ColourSpace::Oklab, ColourSpace::Lab] {
+            for colour in colours {
+                let colour: Srgb = Srgb::from(colour).into_format();
+                let converted = space.to_srgb(space.from_srgb(colour));
+                let difference = (colour - converted).into_components();
+                assert!(difference.0.abs() < 1e-3 && difference.1.abs() < 1e-3 && difference.2.abs() < 1e-3);
+            }
+        }
+
+        // the error is diffused in the space itself, so the share of white matches the perceptual lightness
+        let lightness = |space: ColourSpace, colour: Srgb| match space {
+            ColourSpace::Lab => space.from_srgb(colour).0 / 100.0,
+            _ => space.from_srgb(colour).0,
+        };
+
+        for space in [ColourSpace::Oklab, ColourSpace::Lab] {
+            for value in [64, 128, 192] {
+                let flat: RgbImageRepr = vec![vec![[value; 3]; 64]; 64];
+                let dithered = flat.apply(&FLOYD_STEINBERG.with_colour_space(space).with_palette(ONE_BIT.to_vec()));
+
+                let whites = dithered.iter().flatten().filter(|pixel| pixel[0] == 255).count();
+                let expected = lightness(space, Srgb::from([value; 3]).into_format());
+                assert!((whites as f32 / (64 * 64) as f32 - expected).abs() < 0.02);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_light_dithering_test() {
+        // sRGB 188 is roughly half of the light of sRGB 255.
+        let flat: RgbImageRepr = vec![vec![[188; 3]; 64]; 64];
+
+        let white_ratio = |image: RgbImageRepr| {
+            let whites = image.iter().flatten().filter(|pixel| pixel[0] == 255).count();
+            whites as f32 / (64 * 64) as f32
+        };
+
+        let dithered = flat.clone().apply(&FLOYD_STEINBERG
+            .with_colour_space(ColourSpace::LinearRgb)
+            .with_palette(ONE_BIT.to_vec()));
+        assert!((white_ratio(dithered) - 0.5).abs() < 0.02);
+
+        let dithered = flat.apply(&Ordered::bayer(8, ONE_BIT.to_vec())
+            .with_colour_space(ColourSpace::LinearRgb)
+            .with_distance(ColourDistance::LinearRgb));
+        assert!((white_ratio(dithered) - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn blue_noise_test() {
+        let flat: RgbImageRepr = vec![vec![[128; 3]; 32]; 32];
+
+        let first = flat.clone().apply(&Ordered::blue_noise(16, 7, ONE_BIT.to_vec()));
+        let second = flat.clone().apply(&Ordered::blue_noise(16, 7, ONE_BIT.to_vec()));
+        assert_eq!(first, second);
+
+        let whites = first.iter().flatten().filter(|pixel| pixel[0] == 255).count();
+        assert!(whites.abs_diff(32 * 32 / 2) < 16);
+    }
+
+    #[test]
+    fn noise_dithering_test() {
+        let flat: RgbImageRepr = vec![vec![[128; 3]; 64]; 64];
+
+        let white_ratio = |image: &RgbImageRepr| {
+            let whites = image.iter().flatten().filter(|pixel| pixel[0] == 255).count();
+            whites as f32 / (64 * 64) as f32
+        };
+
+        let ditherers = [
+            flat.clone().apply(&Ordered::white_noise(3, ONE_BIT.to_vec())),
+            flat.clone().apply(&Ordered::new(NoiseMap::white(3, NoiseDistribution::Triangular), ONE_BIT.to_vec())),
+            flat.clone().apply(&Ordered::interleaved_gradient_noise(3, ONE_BIT.to_vec())),
+        ];
+
+        for dithered in ditherers.iter() {
+            assert!((white_ratio(dithered) - 0.5).abs() < 0.05);
+        }
+
+        assert_eq!(ditherers[0], flat.clone().apply(&Ordered::white_noise(3, ONE_BIT.to_vec())));
+        assert_ne!(ditherers[0], flat.apply(&Ordered::white_noise(4, ONE_BIT.to_vec())));
+
+        // every threshold stays within the usual range, including the triangular distribution
+        let triangular = NoiseMap::white(3, NoiseDistribution::Triangular);
+        assert!((0..64).all(|y| (0..64).all(|x| (0.0..1.0).contains(&triangular.threshold(x, y)))));
+
+        // the noise doesn't tile, so there are no repeats or seams every 256 pixels
+        assert_eq!(NoiseMap::interleaved_gradient(3).dimensions(), None);
+        let wide: RgbImageRepr = vec![vec![[128; 3]; 512]; 8];
+        for ditherer in [Ordered::white_noise(3, ONE_BIT.to_vec()), Ordered::interleaved_gradient_noise(3, ONE_BIT.to_vec())] {
+            let dithered = wide.clone().apply(&ditherer);
+            assert!(dithered.iter().all(|row| row[..256] != row[256..]));
+        }
+    }
+
+    #[test]
+    fn ordered_bayer_test() {
+        let ramp: RgbImageRepr = (0..16)
+            .map(|y| (0..=255).map(|x| [x, y * 16, 255 - x]).collect())
+            .collect();
+
+        for size in [2, 4, 8] {
+            assert_eq!(
+                ramp.clone().apply(&Bayer::new(size, EIGHT_BIT.to_vec())),
+                ramp.clone().apply(&Ordered::new(ThresholdMatrix::bayer(size), EIGHT_BIT.to_vec())),
+            );
+            assert_eq!(
+                ramp.clone().apply(&Ordered::bayer(16, EIGHT_BIT.to_vec()).with_map(ThresholdMatrix::bayer(size))),
+                ramp.clone().apply(&Bayer::new(16, EIGHT_BIT.to_vec()).with_matrix_size(size)),
+            );
+        }
+
+        let ranks = vec![
+            vec![ 0, 12,  3, 15],
+            vec![ 8,  4, 11,  7],
+            vec![ 2, 14,  1, 13],
+            vec![10,  6,  9,  5],
+        ];
+        let (bayer, from_ranks) = (ThresholdMatrix::bayer(4), ThresholdMatrix::new(ranks));
+        assert!((0..4).all(|y| (0..4).all(|x| bayer.threshold(x, y) == from_ranks.threshold(x, y))));
+
+        // other sizes are rounded up to the next power of two
+        assert_eq!(ThresholdMatrix::bayer(0).dimensions(), Some((1, 1)));
+        assert_eq!(ThresholdMatrix::bayer(3).dimensions(), Some((4, 4)));
+        assert_eq!(ThresholdMatrix::bayer(5).dimensions(), Some((8, 8)));
+    }
+
+    #[test]
+    fn knoll_pattern_test() {
+        // halfway between red and blue, which `Bayer` can't mix since purple is equally far from both
+        let flat: RgbImageRepr = vec![vec![[128, 0, 128]; 16]; 16];
+        let palette = vec![Srgb::new(1.0, 0.0, 0.0), Srgb::new(0.0, 0.0, 1.0)];
+
+        let dithered = flat.apply(&Knoll::new(ThresholdMatrix::bayer(4), palette));
+        let reds = dithered.iter().flatten().filter(|pixel| *pixel == &[255, 0, 0]).count();
+        assert!(reds.abs_diff(16 * 16 / 2) <= 16);
+    }
+
+    #[test]
+    fn palette_spread_test() {
+        let flat: RgbImageRepr = vec![vec![[200; 3]; 64]; 64];
+
+        let white_ratio = |image: RgbImageRepr| {
+            let whites = image.iter().flatten().filter(|pixel| pixel[0] == 255).count();
+            whites as f32 / (64 * 64) as f32
+        };
+
+        let dithered = flat.clone().apply(&Ordered::bayer(8, ONE_BIT.to_vec()));
+        assert!((white_ratio(dithered) - 200.0 / 255.0).abs() < 0.02);
+
+        let dithered = flat.apply(&Ordered::bayer(8, ONE_BIT.to_vec()).with_spread(Spread::Fixed(1.0 / 3.0)));
+        assert_eq!(white_ratio(dithered), 1.0);
+
+        // opponent spaces only spread lightness, so grays stay gray
+        let ramp = gray_ramp(256, 16);
+        for colour_space in [ColourSpace::Lab, ColourSpace::Oklab] {
+            let dithered = ramp.clone().apply(&Ordered::bayer(8, EIGHT_BIT.to_vec()).with_colour_space(colour_space));
+            assert!(dithered.iter().flatten().all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]));
+        }
+
+        // and measure the distance between palette colours by their lightness alone
+        let palette = [Srgb::new(0.0, 0.0, 0.0), Srgb::new(1.0, 0.0, 0.0), Srgb::new(1.0, 1.0, 1.0)];
+        let [black, red, white] = palette.map(|colour| ColourSpace::Lab.from_srgb(colour).0);
+        let expected = ((red - black) + (white - red) + (white - red)) / 3.0;
+
+        let spread = Spread::Palette.resolve(&PaletteMatcher::new(&palette, ColourDistance::WeightedRgb), ColourSpace::Lab);
+        assert!((spread[0] - expected).abs() < 1e-4);
+        assert_eq!(spread[1..], [0.0, 0.0]);
+    }
+
     #[test]
     fn filter_effects_test() -> UtilResult<()> {
         let image = get_image()?;
@@ -282,13 +1117,66 @@ mod test {
             BURKES,
             SIERRA,
             SIERRA_TWO_ROW,
-            SIERRA_LITE
+            SIERRA_LITE,
+            OSTROMOUKHOV,
         ];
 
         for propagator in error_propagators.into_iter() {
             image.clone()
                 .apply(&propagator.with_palette(palette.clone()))
                 .save(format!("data/dither/{}{}.png", propagator.name, postfix))?;
+            image.clone()
+                .apply(&propagator.with_scan_order(ScanOrder::Serpentine).with_palette(palette.clone()))
+                .save(format!("data/dither/{}-serpentine{}.png", propagator.name, postfix))?;
+            image.clone()
+                .apply(&Hybrid::new(propagator.with_palette(palette.clone()), ThresholdMatrix::bayer(8)))
+                .save(format!("data/dither/{}-hybrid-bayer-8x8{}.png", propagator.name, postfix))?;
+        }
+
+        let distances = [
+            ("linear-rgb", ColourDistance::LinearRgb),
+            ("cie76", ColourDistance::Cie76),
+            ("cie94", ColourDistance::Cie94),
+            ("ciede2000", ColourDistance::Ciede2000),
+            ("oklab", ColourDistance::Oklab),
+            ("hyab", ColourDistance::HyAB),
+        ];
+
+        for (name, distance) in distances.into_iter() {
+            image.clone()
+                .apply(&FLOYD_STEINBERG.with_distance(distance).with_palette(palette.clone()))
+                .save(format!("data/dither/{}-{}{}.png", FLOYD_STEINBERG.name, name, postfix))?;
+            image.clone()
+                .apply(&Ordered::bayer(8, palette.clone()).with_distance(distance))
+                .save(format!("data/dither/bayer-8x8-{}{}.png", name, postfix))?;
+        }
+
+        image.clone()
+            .apply(&FLOYD_STEINBERG.with_colour_space(ColourSpace::LinearRgb).with_palette(palette.clone()))
+            .save(format!("data/dither/{}-linear{}.png", FLOYD_STEINBERG.name, postfix))?;
+        image.clone()
+            .apply(&Ordered::bayer(8, palette.clone())
+                .with_colour_space(ColourSpace::LinearRgb)
+                .with_distance(ColourDistance::LinearRgb))
+            .save(format!("data/dither/bayer-8x8-linear{}.png", postfix))?;
+
+        image.clone()
+            .apply(&FLOYD_STEINBERG.with_strength(0.5).with_error_limit(0.25).with_palette(palette.clone()))
+            .save(format!("data/dither/{}-reduced{}.png", FLOYD_STEINBERG.name, postfix))?;
+        image.clone()
+            .apply(&FLOYD_STEINBERG.with_preserve_exact_matches(true).with_palette(palette.clone()))
+            .save(format!("data/dither/{}-preserve-exact{}.png", FLOYD_STEINBERG.name, postfix))?;
+        image.clone()
+            .apply(&FLOYD_STEINBERG
+                .with_edge_detection(EdgeDetection::Sobel(0.25), 0.0)
+                .with_sharpening(0.5)
+                .with_palette(palette.clone()))
+            .save(format!("data/dither/{}-edge-aware{}.png", FLOYD_STEINBERG.name, postfix))?;
+
+        for (name, space) in [("oklab", ColourSpace::Oklab), ("lab", ColourSpace::Lab)].into_iter() {
+            image.clone()
+                .apply(&FLOYD_STEINBERG.with_colour_space(space).with_palette(palette.clone()))
+                .save(format!("data/dither/{}-{}-space{}.png", FLOYD_STEINBERG.name, name, postfix))?;
         }
 
         image.clone().apply(&Bayer::new(2, palette.clone()))
@@ -299,6 +1187,60 @@ mod test {
             .save(format!("data/dither/bayer-8x8{}.png", postfix))?;
         image.clone().apply(&Bayer::new(16, palette.clone()))
             .save(format!("data/dither/bayer-16x16{}.png", postfix))?;
+        image.clone().apply(&Ordered::bayer(8, palette.clone()).with_spread(Spread::Fixed(1.0 / 3.0)))
+            .save(format!("data/dither/bayer-8x8-fixed-spread{}.png", postfix))?;
+        image.clone().apply(&Ordered::blue_noise(32, 0, palette.clone()))
+            .save(format!("data/dither/blue-noise-32x32{}.png", postfix))?;
+        let threshold_maps = [
+            ("clustered-dot-8x8", ThresholdMatrix::clustered_dot(8)),
+            ("horizontal-lines-6", ThresholdMatrix::horizontal_lines(6)),
+            ("vertical-lines-6", ThresholdMatrix::vertical_lines(6)),
+            ("diagonal-8", ThresholdMatrix::diagonal(8)),
+            ("ordered-3x3", ThresholdMatrix::three_by_three()),
+            ("ordered-5x5", ThresholdMatrix::five_by_five()),
+        ];
+
+        for (name, map) in threshold_maps.into_iter() {
+            image.clone().apply(&Ordered::new(map, palette.clone()))
+                .save(format!("data/dither/{}{}.png", name, postfix))?;
+        }
+
+        image.clone().apply(&Ordered::new(PerChannel::rotated(ThresholdMatrix::bayer(8)), palette.clone()))
+            .save(format!("data/dither/bayer-8x8-rotated{}.png", postfix))?;
+
+        image.clone().apply(&Yliluoma::new(ThresholdMatrix::bayer(8), palette.clone()))
+            .save(format!("data/dither/yliluoma-2{}.png", postfix))?;
+
+        // algorithm 1 searches every pair of colours, which is too slow for larger palettes
+        if palette.len() <= 16 {
+            image.clone()
+                .apply(&Yliluoma::new(ThresholdMatrix::bayer(8), palette.clone()).with_algorithm(YliluomaAlgorithm::One))
+                .save(format!("data/dither/yliluoma-1{}.png", postfix))?;
+        }
+
+        image.clone().apply(&Ordered::white_noise(0, palette.clone()))
+            .save(format!("data/dither/white-noise{}.png", postfix))?;
+
+        image.clone().apply(&Ordered::new(NoiseMap::white(0, NoiseDistribution::Triangular), palette.clone()))
+            .save(format!("data/dither/white-noise-triangular{}.png", postfix))?;
+
+        image.clone().apply(&Ordered::interleaved_gradient_noise(0, palette.clone()))
+            .save(format!("data/dither/interleaved-gradient-noise{}.png", postfix))?;
+
+        image.clone().apply(&Knoll::new(ThresholdMatrix::bayer(8), palette.clone()))
+            .save(format!("data/dither/knoll{}.png", postfix))?;
+
+        image.clone().apply(&DotDiffusion::new(palette.clone()))
+            .save(format!("data/dither/dot-diffusion{}.png", postfix))?;
+
+        // every pass tries every palette colour for every pixel, which is too slow for larger palettes
+        if palette.len() <= 16 {
+            image.clone().apply(&DirectBinarySearch::new(Ordered::bayer(8, palette.clone()), palette.clone()))
+                .save(format!("data/dither/direct-binary-search{}.png", postfix))?;
+        }
+
+        image.clone().apply(&Riemersma::new(palette.clone()))
+            .save(format!("data/dither/riemersma{}.png", postfix))?;
         Ok(())
     }
 }
@@ -1,2 +1,3 @@
 pub mod image;
-pub mod numops;
\ No newline at end of file
+pub mod numops;
+pub mod random;
\ No newline at end of file
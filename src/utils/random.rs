@@ -0,0 +1,35 @@
+/// A small, seedable pseudo-random number generator based on SplitMix64.
+///
+/// Not suitable for anything cryptographic - it's only used to make randomized effects reproducible.
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Creates a generator for a specific pair of coordinates, so that per-pixel randomness
+    /// doesn't depend on the order in which the pixels are visited.
+    pub fn at(seed: u64, x: usize, y: usize) -> Self {
+        let hashed = Self::new(Self::new(seed).next_u64() ^ x as u64).next_u64();
+        Self::new(hashed ^ (y as u64).rotate_left(32))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random integer in the range `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns a random float in the range `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}